@@ -0,0 +1,190 @@
+//! Drain iterators deciding about an element based on the last kept element.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// Visits the elements until `func`, called with the last kept element
+/// and the current element, returns true. The first element is kept
+/// unconditionally.
+///
+/// Returns true if an element to drain was found, it is then the first
+/// not yet visited element.
+fn seek_with_last<I, F>(raw: &mut RawDrain<'_, I>, func: &mut F) -> bool
+    where F: FnMut(&mut I, &mut I) -> bool
+{
+    loop {
+        let gap_pos = raw.front_gap_idx();
+        if gap_pos == 0 {
+            // nothing kept yet to compare with
+            if raw.keep_front(1) == 0 {
+                return false;
+            }
+            continue;
+        }
+        let last = raw.slot(gap_pos - 1);
+        // the last kept element is always before the current one
+        match raw.decide_front(OnPanic::Leak, |_, current| func(unsafe { &mut *last }, current)) {
+            Some(decision) if decision.is_drain() => return true,
+            Some(_) => { raw.keep_front(1); },
+            None => return false
+        }
+    }
+}
+
+/// Iterator merging elements into the last kept element.
+///
+/// See `VecDrainWhereExt::e_coalesce_where`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecCoalesceWhere<'a, Item: 'a, Func> {
+    raw: RawDrain<'a, Item>,
+    merge: Func
+}
+
+impl<'a, I: 'a, F> VecCoalesceWhere<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, merge: F) -> Self {
+        VecCoalesceWhere { raw, merge }
+    }
+}
+
+impl<'a, I: 'a, F> Iterator for VecCoalesceWhere<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if seek_with_last(&mut self.raw, &mut self.merge) {
+            self.raw.take_front()
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.remaining()))
+    }
+}
+
+impl<'a, I: 'a, F> FusedIterator for VecCoalesceWhere<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{}
+
+/// Iterator removing pairs of adjacent elements cancelling each other.
+///
+/// See `VecDrainWhereExt::e_cancel_pairs_where`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecCancelPairs<'a, Item: 'a, Func> {
+    raw: RawDrain<'a, Item>,
+    cancels: Func
+}
+
+impl<'a, I: 'a, F> VecCancelPairs<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, cancels: F) -> Self {
+        VecCancelPairs { raw, cancels }
+    }
+}
+
+impl<'a, I: 'a, F> Iterator for VecCancelPairs<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{
+    type Item = (I, I);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !seek_with_last(&mut self.raw, &mut self.cancels) {
+            return None;
+        }
+        let current = self.raw.take_front()?;
+        let last = self.raw.take_last_kept();
+        Some((last, current))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.remaining()))
+    }
+}
+
+impl<'a, I: 'a, F> FusedIterator for VecCancelPairs<'a, I, F>
+    where F: FnMut(&mut I, &mut I) -> bool
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    #[test]
+    fn merge_overlapping_intervals() {
+        let mut data = vec![(1, 3), (2, 5), (4, 6), (8, 9), (9, 12), (14, 15)];
+        let merged = data.e_coalesce_where(|last, current| {
+            if current.0 <= last.1 {
+                last.1 = last.1.max(current.1);
+                true
+            } else {
+                false
+            }
+        }).count();
+        assert_eq!(merged, 3);
+        assert_eq!(data, vec![(1, 6), (8, 12), (14, 15)]);
+    }
+
+    fn coalesce_like_dedup(data: Vec<u8>) -> TestResult {
+        let data = data.into_iter().map(|x| x % 4).collect::<Vec<_>>();
+        let mut expected = data.clone();
+        expected.dedup();
+        let mut data2 = data.clone();
+        let drained = data2.e_coalesce_where(|last, current| last == current).count();
+        if data2 != expected || drained != data.len() - expected.len() {
+            return TestResult::error(format!("{:?} != {:?}", data2, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_coalesce_like_dedup() {
+        ::quickcheck::quickcheck(coalesce_like_dedup as fn(Vec<u8>) -> TestResult);
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum Token { Open, Close, Other }
+
+    #[test]
+    fn cancel_open_close() {
+        use self::Token::*;
+        let mut data = vec![Other, Open, Open, Close, Other, Open, Close, Close, Close, Open];
+        let pairs = data.e_cancel_pairs_where(|last, current| *last == Open && *current == Close)
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(Open, Close), (Open, Close)]);
+        assert_eq!(data, vec![Other, Open, Other, Close, Close, Open]);
+    }
+
+    fn cancel_like_stack(ops: Vec<i8>) -> TestResult {
+        let mut stack = Vec::new();
+        let mut expected_pairs = Vec::new();
+        for op in ops.iter().cloned() {
+            if stack.last().map(|last: &i8| *last as i16 == -(op as i16) && op != 0).unwrap_or(false) {
+                expected_pairs.push((stack.pop().unwrap(), op));
+            } else {
+                stack.push(op);
+            }
+        }
+        let mut data = ops.clone();
+        let pairs = data.e_cancel_pairs_where(|last, current| {
+            *last as i16 == -(*current as i16) && *current != 0
+        }).collect::<Vec<_>>();
+        if pairs != expected_pairs || data != stack {
+            return TestResult::error(format!("{:?}/{:?} != {:?}/{:?}", pairs, data, expected_pairs, stack));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_cancel_like_stack() {
+        ::quickcheck::quickcheck(cancel_like_stack as fn(Vec<i8>) -> TestResult);
+    }
+}
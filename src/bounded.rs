@@ -0,0 +1,120 @@
+//! Drain iterator draining at most a given number of elements.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// Iterator for draining at most `max` elements of a vector conditionally.
+///
+/// See `VecDrainWhereExt::e_drain_where_max`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecDrainWhereMax<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred,
+    left: usize
+}
+
+impl<'a, I: 'a, P> VecDrainWhereMax<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    pub(crate) fn new(mut raw: RawDrain<'a, I>, max: usize, predicate: P) -> Self {
+        if max == 0 {
+            raw.stop();
+        }
+        VecDrainWhereMax { raw, predicate, left: max }
+    }
+
+    fn count_drained(&mut self, drained: Option<I>) -> Option<I> {
+        if drained.is_some() {
+            self.left -= 1;
+            if self.left == 0 {
+                self.raw.stop();
+            }
+        }
+        drained
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecDrainWhereMax<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let drained = {
+            let predicate = &mut self.predicate;
+            self.raw.next_front(OnPanic::Leak, |_, item| predicate(item))
+                .map(|(_, item)| item)
+        };
+        self.count_drained(drained)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.upper_bound().min(self.left)))
+    }
+}
+
+impl<'a, I: 'a, P> DoubleEndedIterator for VecDrainWhereMax<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let drained = {
+            let predicate = &mut self.predicate;
+            self.raw.next_back(OnPanic::Leak, |_, item| predicate(item))
+                .map(|(_, item)| item)
+        };
+        self.count_drained(drained)
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecDrainWhereMax<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn max_with_mask(mask: Vec<bool>, max: usize) -> TestResult {
+        let max = max % (mask.len() + 2);
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut visited = 0;
+        let drained = data.e_drain_where_max(max, |el| {
+            visited += 1;
+            mask[*el]
+        }).collect::<Vec<_>>();
+
+        let expected_drained = (0..mask.len()).filter(|el| mask[*el]).take(max).collect::<Vec<_>>();
+        if drained != expected_drained {
+            return TestResult::error(format!("drained {:?}, exp {:?}", drained, expected_drained));
+        }
+        let expected_visited = if drained.len() == max {
+            drained.last().map(|el| *el + 1).unwrap_or(0)
+        } else {
+            mask.len()
+        };
+        if visited != expected_visited {
+            return TestResult::error(format!("visited {}, exp {}", visited, expected_visited));
+        }
+        let expected = (0..mask.len()).filter(|el| !expected_drained.contains(el)).collect::<Vec<_>>();
+        if data != expected {
+            return TestResult::error(format!("remaining {:?}, exp {:?}", data, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_max_with_mask() {
+        ::quickcheck::quickcheck(max_with_mask as fn(Vec<bool>, usize) -> TestResult);
+    }
+
+    #[test]
+    fn max_from_the_back() {
+        let mut data = vec![1, 2, 3, 4, 5, 6];
+        let drained = data.e_drain_where_max(2, |x| *x % 2 == 1).rev().collect::<Vec<_>>();
+        assert_eq!(drained, vec![5, 3]);
+        assert_eq!(data, vec![1, 2, 4, 6]);
+    }
+}
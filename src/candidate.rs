@@ -0,0 +1,166 @@
+//! Drain yielding a guard for each element instead of calling a predicate.
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+
+use raw::RawDrain;
+
+/// Yields a `DrainCandidate` for each element of the vector.
+///
+/// This can't implement `Iterator` as each candidate borrows
+/// from it, use `next_candidate` in a `while let` loop instead.
+///
+/// When dropped all elements not yet visited are kept.
+///
+/// See `VecDrainWhereExt::e_drain_candidates`.
+#[derive(Debug)]
+pub struct DrainCandidates<'a, Item: 'a> {
+    raw: RawDrain<'a, Item>
+}
+
+impl<'a, I: 'a> DrainCandidates<'a, I> {
+    pub(crate) fn new(raw: RawDrain<'a, I>) -> Self {
+        DrainCandidates { raw }
+    }
+
+    /// Returns the next element as candidate for draining.
+    ///
+    /// Returns `None` if all elements where visited.
+    pub fn next_candidate(&mut self) -> Option<DrainCandidate<'_, 'a, I>> {
+        let item = self.raw.front_mut()? as *mut I;
+        Some(DrainCandidate { raw: &mut self.raw, item })
+    }
+
+    /// Number of elements not yet visited.
+    pub fn remaining(&self) -> usize {
+        self.raw.remaining()
+    }
+}
+
+/// Element which is drained if `take` is called and kept otherwise.
+///
+/// Dereferences to the element. If the guard is dropped without
+/// calling `take` (including because of a panic) the element is
+/// kept. If it is leaked (e.g. `mem::forget`) the cursor stays
+/// where it is, so the next `next_candidate` yields the same
+/// element again.
+#[derive(Debug)]
+pub struct DrainCandidate<'r, 'a: 'r, Item: 'a> {
+    raw: &'r mut RawDrain<'a, Item>,
+    item: *mut Item
+}
+
+impl<'r, 'a: 'r, I: 'a> DrainCandidate<'r, 'a, I> {
+
+    /// Drains the element.
+    pub fn take(self) -> I {
+        let mut this = ManuallyDrop::new(self);
+        this.raw.take_front().expect("candidate is first unvisited element")
+    }
+
+    /// Keeps the element, this is the same as dropping the candidate.
+    pub fn keep(self) {}
+}
+
+impl<'r, 'a: 'r, I: 'a> Deref for DrainCandidate<'r, 'a, I> {
+    type Target = I;
+
+    fn deref(&self) -> &I {
+        unsafe { &*self.item }
+    }
+}
+
+impl<'r, 'a: 'r, I: 'a> DerefMut for DrainCandidate<'r, 'a, I> {
+    fn deref_mut(&mut self) -> &mut I {
+        unsafe { &mut *self.item }
+    }
+}
+
+impl<'r, 'a: 'r, I: 'a> Drop for DrainCandidate<'r, 'a, I> {
+    fn drop(&mut self) {
+        self.raw.keep_front(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn parse_even(s: &str) -> Result<bool, ::std::num::ParseIntError> {
+        s.parse::<u32>().map(|n| n % 2 == 0)
+    }
+
+    fn take_even(data: &mut Vec<&'static str>) -> Result<Vec<&'static str>, ::std::num::ParseIntError> {
+        let mut drained = Vec::new();
+        let mut candidates = data.e_drain_candidates();
+        while let Some(candidate) = candidates.next_candidate() {
+            if parse_even(&candidate)? {
+                drained.push(candidate.take());
+            }
+        }
+        Ok(drained)
+    }
+
+    #[test]
+    fn question_mark_in_decision() {
+        let mut data = vec!["1", "2", "3", "4"];
+        assert_eq!(take_even(&mut data), Ok(vec!["2", "4"]));
+        assert_eq!(data, vec!["1", "3"]);
+
+        let mut data = vec!["1", "2", "x", "4"];
+        assert!(take_even(&mut data).is_err());
+        assert_eq!(data, vec!["1", "x", "4"]);
+    }
+
+    #[test]
+    fn forgotten_candidate_keeps_rest() {
+        let mut data = vec![1, 2, 3, 4];
+        {
+            let mut candidates = data.e_drain_candidates();
+            candidates.next_candidate().unwrap().take();
+            let mut candidate = candidates.next_candidate().unwrap();
+            *candidate = 20;
+            mem::forget(candidate);
+            assert_eq!(candidates.remaining(), 3);
+            assert_eq!(candidates.next_candidate().map(|c| *c), Some(20));
+        }
+        assert_eq!(data, vec![20, 3, 4]);
+    }
+
+    fn candidates_with_mask(mask: Vec<bool>, panic_at: Option<usize>) -> TestResult {
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut drained = Vec::new();
+        let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            let mut candidates = data.e_drain_candidates();
+            while let Some(candidate) = candidates.next_candidate() {
+                if Some(*candidate) == panic_at {
+                    panic!("-- yes panic --");
+                }
+                if mask[*candidate] {
+                    drained.push(candidate.take());
+                } else {
+                    candidate.keep();
+                }
+            }
+        }));
+        let panicked = panic_at.map(|idx| idx < mask.len()).unwrap_or(false);
+        if res.is_err() != panicked {
+            return TestResult::error("unexpected panic result");
+        }
+        let visited = panic_at.unwrap_or(mask.len()).min(mask.len());
+        let expected = (0..mask.len()).filter(|el| *el >= visited || !mask[*el]).collect::<Vec<_>>();
+        if data != expected {
+            return TestResult::error(format!("remaining {:?}, exp {:?}", data, expected));
+        }
+        if drained != (0..visited).filter(|el| mask[*el]).collect::<Vec<_>>() {
+            return TestResult::error(format!("drained {:?}", drained));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_candidates_with_mask() {
+        ::quickcheck::quickcheck(candidates_with_mask as fn(Vec<bool>, Option<usize>) -> TestResult);
+    }
+}
@@ -0,0 +1,169 @@
+//! Drain iterator whose predicate can look at the surrounding elements.
+use std::slice;
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// The element to decide about together with the elements around it.
+///
+/// Passed to the predicate of `e_drain_where_ctx`.
+#[derive(Debug)]
+pub struct DrainContext<'c, Item: 'c> {
+    kept: &'c [Item],
+    current: &'c mut Item,
+    rest: &'c [Item],
+    drained_count: usize,
+    kept_count: usize
+}
+
+impl<'c, I: 'c> DrainContext<'c, I> {
+
+    /// The elements kept so far, the last one is the one directly before
+    /// the current element once the draining is done.
+    pub fn kept(&self) -> &[I] {
+        self.kept
+    }
+
+    /// The element to decide about.
+    pub fn current(&self) -> &I {
+        self.current
+    }
+
+    /// The element to decide about.
+    pub fn current_mut(&mut self) -> &mut I {
+        self.current
+    }
+
+    /// The elements after the current element, which were not yet visited.
+    pub fn rest(&self) -> &[I] {
+        self.rest
+    }
+
+    /// Number of elements drained so far.
+    pub fn drained_count(&self) -> usize {
+        self.drained_count
+    }
+
+    /// Number of elements kept so far.
+    pub fn kept_count(&self) -> usize {
+        self.kept_count
+    }
+}
+
+/// Iterator for draining a vector with a predicate getting a `DrainContext`.
+///
+/// See `VecDrainWhereExt::e_drain_where_ctx`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecDrainWhereCtx<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred
+}
+
+impl<'a, I: 'a, P> VecDrainWhereCtx<'a, I, P>
+    where P: FnMut(DrainContext<'_, I>) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        VecDrainWhereCtx { raw, predicate }
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecDrainWhereCtx<'a, I, P>
+    where P: FnMut(DrainContext<'_, I>) -> bool
+{
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let base = self.raw.slot(0) as *const I;
+            let gap_pos = self.raw.front_gap_idx();
+            let pos = self.raw.front_idx();
+            let rest_len = self.raw.remaining().saturating_sub(1);
+            let report = self.raw.report();
+            let predicate = &mut self.predicate;
+            let decision = self.raw.decide_front(OnPanic::Leak, |_, current| {
+                // the kept elements, the current element and the rest
+                // are disjoint sections of the vector
+                let (kept, rest) = unsafe {(
+                    slice::from_raw_parts(base, gap_pos),
+                    slice::from_raw_parts(base.add(pos + 1), rest_len)
+                )};
+                predicate(DrainContext {
+                    kept,
+                    current,
+                    rest,
+                    drained_count: report.drained,
+                    kept_count: report.kept
+                })
+            })?;
+            if decision.is_drain() {
+                return self.raw.take_front();
+            }
+            self.raw.keep_front(1);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.remaining()))
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecDrainWhereCtx<'a, I, P>
+    where P: FnMut(DrainContext<'_, I>) -> bool
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    #[test]
+    fn drain_if_same_as_last_kept() {
+        let mut data = vec![1, 1, 2, 2, 2, 1, 3, 3];
+        let drained = data.e_drain_where_ctx(|ctx| ctx.kept().last() == Some(ctx.current()))
+            .collect::<Vec<_>>();
+        assert_eq!(drained, vec![1, 2, 2, 3]);
+        assert_eq!(data, vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn drain_if_newer_entry_follows() {
+        let mut data = vec![(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (2, 'e')];
+        let drained = data.e_drain_where_ctx(|ctx| {
+            let id = ctx.current().0;
+            ctx.rest().iter().any(|entry| entry.0 == id)
+        }).collect::<Vec<_>>();
+        assert_eq!(drained, vec![(1, 'a'), (2, 'b')]);
+        assert_eq!(data, vec![(1, 'c'), (3, 'd'), (2, 'e')]);
+    }
+
+    fn context_with_mask(mask: Vec<bool>) -> TestResult {
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut failed = None;
+        let drained = data.e_drain_where_ctx(|mut ctx| {
+            let el = *ctx.current();
+            let kept = (0..el).filter(|el| !mask[*el]).map(|el| el + 100).collect::<Vec<_>>();
+            let rest = (el + 1..mask.len()).collect::<Vec<_>>();
+            if ctx.kept() != &kept[..] || ctx.rest() != &rest[..]
+                || ctx.kept_count() != kept.len() || ctx.drained_count() != el - kept.len()
+            {
+                failed = Some(format!("unexpected context for {}: {:?}", el, ctx));
+            }
+            *ctx.current_mut() += 100;
+            mask[el]
+        }).collect::<Vec<_>>();
+        if let Some(failure) = failed {
+            return TestResult::error(failure);
+        }
+        if drained != (0..mask.len()).filter(|el| mask[*el]).map(|el| el + 100).collect::<Vec<_>>() {
+            return TestResult::error(format!("drained {:?}", drained));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_context_with_mask() {
+        ::quickcheck::quickcheck(context_with_mask as fn(Vec<bool>) -> TestResult);
+    }
+}
@@ -0,0 +1,214 @@
+//! Cursor for editing a vector in place in a single pass.
+use raw::RawDrain;
+
+/// Cursor walking once over a vector, deciding for each element
+/// if it is kept, removed or replaced.
+///
+/// Removing elements leaves a gap before the current element, which
+/// is closed when the cursor is dropped. Elements can be inserted
+/// into that gap (`insert_before`) without shifting any elements.
+///
+/// When dropped all elements not yet visited are kept.
+///
+/// See `VecDrainWhereExt::e_drain_cursor`.
+#[derive(Debug)]
+pub struct DrainCursor<'a, Item: 'a> {
+    raw: RawDrain<'a, Item>
+}
+
+impl<'a, I: 'a> DrainCursor<'a, I> {
+    pub(crate) fn new(raw: RawDrain<'a, I>) -> Self {
+        DrainCursor { raw }
+    }
+
+    /// Returns the current element, `None` if all elements where visited.
+    pub fn current_mut(&mut self) -> Option<&mut I> {
+        self.raw.front_mut()
+    }
+
+    /// Number of elements not yet visited (including the current one).
+    pub fn remaining(&self) -> usize {
+        self.raw.remaining()
+    }
+
+    /// Number of elements which can be inserted with `insert_before`.
+    ///
+    /// This is the number of removed elements minus the number of
+    /// inserted elements.
+    pub fn gap_len(&self) -> usize {
+        self.raw.front_gap_len()
+    }
+
+    /// Keeps the current element and advances to the next one.
+    ///
+    /// Returns false if there is no current element.
+    pub fn keep(&mut self) -> bool {
+        self.raw.keep_front(1) == 1
+    }
+
+    /// Keeps the next `n` elements (starting with the current one).
+    ///
+    /// This moves them in one go. Returns the number of elements
+    /// kept, which is less than `n` if there are less elements left.
+    pub fn skip_keep(&mut self, n: usize) -> usize {
+        self.raw.keep_front(n)
+    }
+
+    /// Removes the current element and advances to the next one.
+    ///
+    /// Returns `None` if there is no current element.
+    pub fn remove(&mut self) -> Option<I> {
+        self.raw.take_front()
+    }
+
+    /// Replaces the current element, keeps the new element and
+    /// advances to the next one.
+    ///
+    /// Returns the replaced element or `Err(item)` if there is no
+    /// current element.
+    pub fn replace(&mut self, item: I) -> Result<I, I> {
+        match self.raw.take_front() {
+            Some(old) => {
+                self.raw.put_front(item);
+                Ok(old)
+            },
+            None => Err(item)
+        }
+    }
+
+    /// Inserts an element before the current one (after all elements
+    /// kept so far).
+    ///
+    /// This is only possible if there is space in the gap left by
+    /// removed elements (see `gap_len`), if not `Err(item)` is returned.
+    pub fn insert_before(&mut self, item: I) -> Result<(), I> {
+        if self.raw.front_gap_len() > 0 {
+            self.raw.put_front(item);
+            Ok(())
+        } else {
+            Err(item)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    #[test]
+    fn remove_replace_insert() {
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7];
+        {
+            let mut cursor = data.e_drain_cursor();
+            assert_eq!(cursor.insert_before(0), Err(0));
+            assert!(cursor.keep());
+            assert_eq!(cursor.remove(), Some(2));
+            assert_eq!(cursor.remove(), Some(3));
+            assert_eq!(cursor.gap_len(), 2);
+            assert_eq!(cursor.insert_before(23), Ok(()));
+            assert_eq!(cursor.replace(40), Ok(4));
+            *cursor.current_mut().unwrap() *= 10;
+            assert_eq!(cursor.skip_keep(10), 3);
+            assert_eq!(cursor.current_mut(), None);
+            assert_eq!(cursor.remove(), None);
+            assert_eq!(cursor.replace(8), Err(8));
+            assert!(!cursor.keep());
+            assert_eq!(cursor.insert_before(99), Ok(()));
+        }
+        assert_eq!(data, vec![1, 23, 40, 50, 6, 7, 99]);
+    }
+
+    #[test]
+    fn drop_keeps_rest() {
+        let mut data = vec![1, 2, 3, 4, 5];
+        {
+            let mut cursor = data.e_drain_cursor();
+            cursor.remove();
+            cursor.keep();
+            cursor.remove();
+        }
+        assert_eq!(data, vec![2, 4, 5]);
+    }
+
+    /// Ops: 0 keep, 1 remove, 2 replace, 3 insert_before, 4 skip_keep(2)
+    fn ops_like_model(len: usize, ops: Vec<u8>) -> TestResult {
+        let len = len % 64;
+        let counter = Rc::new(());
+        let mut data = (0..len).map(|el| (el as isize, counter.clone())).collect::<Vec<_>>();
+        let mut model_kept = Vec::new();
+        let mut model_rest = (0..len as isize).collect::<Vec<_>>();
+        model_rest.reverse();
+        let mut gap = 0usize;
+        {
+            let mut cursor = data.e_drain_cursor();
+            for op in ops {
+                match op % 5 {
+                    0 => {
+                        if cursor.keep() != model_rest.pop().map(|el| model_kept.push(el)).is_some() {
+                            return TestResult::error("keep mismatch");
+                        }
+                    },
+                    1 => {
+                        let removed = cursor.remove().map(|el| el.0);
+                        if removed != model_rest.pop() {
+                            return TestResult::error("remove mismatch");
+                        }
+                        if removed.is_some() {
+                            gap += 1;
+                        }
+                    },
+                    2 => {
+                        let res = cursor.replace((-1, counter.clone())).map(|el| el.0).map_err(|el| el.0);
+                        match model_rest.pop() {
+                            Some(el) => {
+                                model_kept.push(-1);
+                                if res != Ok(el) {
+                                    return TestResult::error("replace mismatch");
+                                }
+                            },
+                            None => if res != Err(-1) {
+                                return TestResult::error("replace at end mismatch");
+                            }
+                        }
+                    },
+                    3 => {
+                        let res = cursor.insert_before((-2, counter.clone())).is_ok();
+                        if res != (gap > 0) {
+                            return TestResult::error("insert mismatch");
+                        }
+                        if res {
+                            gap -= 1;
+                            model_kept.push(-2);
+                        }
+                    },
+                    _ => {
+                        let n = cursor.skip_keep(2);
+                        for _ in 0..n {
+                            model_kept.push(model_rest.pop().unwrap());
+                        }
+                    }
+                }
+                if cursor.gap_len() != gap || cursor.remaining() != model_rest.len() {
+                    return TestResult::error("gap/remaining mismatch");
+                }
+            }
+        }
+        model_rest.reverse();
+        model_kept.extend(model_rest);
+        if data.iter().map(|el| el.0).collect::<Vec<_>>() != model_kept {
+            return TestResult::error(format!("result mismatch {:?}", model_kept));
+        }
+        drop(data);
+        if Rc::strong_count(&counter) != 1 {
+            return TestResult::error("leaked elements");
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_ops_like_model() {
+        ::quickcheck::quickcheck(ops_like_model as fn(usize, Vec<u8>) -> TestResult);
+    }
+}
@@ -0,0 +1,153 @@
+//! Drain iterator with a predicate which can decide to stop draining.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// Decision about an element, returned by the predicate of `e_drain_where_decide`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    /// Keep the element in the vector.
+    Keep,
+
+    /// Drain the element.
+    Drain,
+
+    /// Keep the element and stop draining, keeping all elements not yet visited.
+    KeepAndStop,
+
+    /// Drain the element and stop draining, keeping all elements not yet visited.
+    DrainAndStop
+}
+
+impl Decision {
+
+    /// Returns true if the element should be drained.
+    pub fn is_drain(self) -> bool {
+        match self {
+            Decision::Drain | Decision::DrainAndStop => true,
+            Decision::Keep | Decision::KeepAndStop => false
+        }
+    }
+
+    /// Returns true if draining should stop after this element.
+    pub fn is_stop(self) -> bool {
+        match self {
+            Decision::KeepAndStop | Decision::DrainAndStop => true,
+            Decision::Keep | Decision::Drain => false
+        }
+    }
+}
+
+impl From<bool> for Decision {
+    /// `true` is `Decision::Drain`, `false` is `Decision::Keep`.
+    fn from(drain: bool) -> Self {
+        if drain { Decision::Drain } else { Decision::Keep }
+    }
+}
+
+/// Iterator for draining a vector until the predicate decides to stop.
+///
+/// See `VecDrainWhereExt::e_drain_where_decide`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecDrainDecide<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred
+}
+
+impl<'a, I: 'a, P> VecDrainDecide<'a, I, P>
+    where P: FnMut(&mut I) -> Decision
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        VecDrainDecide { raw, predicate }
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecDrainDecide<'a, I, P>
+    where P: FnMut(&mut I) -> Decision
+{
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        self.raw.next_front(OnPanic::Leak, |_, item| predicate(item))
+            .map(|(_, item)| item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.upper_bound()))
+    }
+}
+
+impl<'a, I: 'a, P> DoubleEndedIterator for VecDrainDecide<'a, I, P>
+    where P: FnMut(&mut I) -> Decision
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        self.raw.next_back(OnPanic::Leak, |_, item| predicate(item))
+            .map(|(_, item)| item)
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecDrainDecide<'a, I, P>
+    where P: FnMut(&mut I) -> Decision
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use {VecDrainWhereExt, Decision};
+
+    fn decide_with_mask(mask: Vec<(bool, bool)>) -> TestResult {
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut visited = Vec::new();
+        let drained = data.e_drain_where_decide(|el| {
+            visited.push(*el);
+            match mask[*el] {
+                (false, false) => Decision::Keep,
+                (true, false) => Decision::Drain,
+                (false, true) => Decision::KeepAndStop,
+                (true, true) => Decision::DrainAndStop
+            }
+        }).collect::<Vec<_>>();
+
+        let stop_at = mask.iter().position(|&(_, stop)| stop).unwrap_or(mask.len());
+        if visited != (0..mask.len()).take(stop_at + 1).collect::<Vec<_>>() {
+            return TestResult::error(format!("visited {:?} ({:?})", visited, mask));
+        }
+        let expected_drained = (0..mask.len()).filter(|el| *el <= stop_at && mask[*el].0)
+            .collect::<Vec<_>>();
+        if drained != expected_drained {
+            return TestResult::error(format!("drained {:?}, exp {:?}", drained, expected_drained));
+        }
+        let expected = (0..mask.len()).filter(|el| *el > stop_at || !mask[*el].0)
+            .collect::<Vec<_>>();
+        if data != expected {
+            return TestResult::error(format!("remaining {:?}, exp {:?}", data, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_decide_with_mask() {
+        ::quickcheck::quickcheck(decide_with_mask as fn(Vec<(bool, bool)>) -> TestResult);
+    }
+
+    #[test]
+    fn stop_from_the_back() {
+        let mut data = vec![1, 2, 3, 4, 5, 6];
+        let mut iter = data.e_drain_where_decide(|x| match *x {
+            4 => Decision::KeepAndStop,
+            x if x % 2 == 0 => Decision::Drain,
+            _ => Decision::Keep
+        });
+        assert_eq!(iter.next_back(), Some(6));
+        assert_eq!(iter.size_hint(), (0, Some(5)));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        drop(iter);
+        assert_eq!(data, vec![1, 2, 3, 4, 5]);
+    }
+}
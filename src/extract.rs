@@ -0,0 +1,213 @@
+//! Drain iterator passing the elements by value to the closure.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+
+/// Iterator extracting and mapping elements of a vector.
+///
+/// See `VecDrainWhereExt::e_extract_map`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecExtractMap<'a, Item: 'a, Func> {
+    raw: RawDrain<'a, Item>,
+    func: Func
+}
+
+impl<'a, I: 'a, F> VecExtractMap<'a, I, F> {
+    pub(crate) fn new(raw: RawDrain<'a, I>, func: F) -> Self {
+        VecExtractMap { raw, func }
+    }
+}
+
+impl<'a, I: 'a, F, O> Iterator for VecExtractMap<'a, I, F>
+    where F: FnMut(I) -> Result<O, I>
+{
+    type Item = O;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // if `func` panics the element is owned (and dropped) by it,
+        // the slot it was in is already part of the gap
+        while let Some(item) = self.raw.take_front() {
+            match (self.func)(item) {
+                Ok(out) => return Some(out),
+                Err(item) => self.raw.put_front(item)
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.remaining()))
+    }
+}
+
+impl<'a, I: 'a, F, O> DoubleEndedIterator for VecExtractMap<'a, I, F>
+    where F: FnMut(I) -> Result<O, I>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while let Some(item) = self.raw.take_back() {
+            match (self.func)(item) {
+                Ok(out) => return Some(out),
+                Err(item) => self.raw.put_back(item)
+            }
+        }
+        None
+    }
+}
+
+impl<'a, I: 'a, F, O> FusedIterator for VecExtractMap<'a, I, F>
+    where F: FnMut(I) -> Result<O, I>
+{}
+
+/// Drains all elements matching a pattern, yielding the expression bound for them.
+///
+/// `drain_matching!(vec, Pattern => expr)` is a shorthand for
+/// `vec.e_extract_map(|item| match item { Pattern => Ok(expr), other => Err(other) })`,
+/// i.e. it returns a `VecExtractMap` iterator and all elements
+/// not matching the pattern are kept in order.
+///
+/// Alternative patterns (`A | B`) and a guard (`Pattern if cond`)
+/// can be used like in a `match`. `vec` can be a `Vec` or a
+/// `&mut Vec`.
+///
+/// ```
+/// # #[macro_use] extern crate vec_drain_where;
+/// # fn main() {
+/// enum Msg { Data(Vec<u8>), Ping }
+///
+/// let mut events = vec![Msg::Data(vec![1]), Msg::Ping, Msg::Data(vec![2, 3])];
+/// let bufs = drain_matching!(events, Msg::Data(buf) => buf).collect::<Vec<_>>();
+/// assert_eq!(bufs, vec![vec![1], vec![2, 3]]);
+/// assert_eq!(events.len(), 1);
+/// # }
+/// ```
+///
+/// Like in a `match` only a single guard is accepted:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate vec_drain_where;
+/// # fn main() {
+/// let mut data = vec![Some(1), None, Some(4)];
+/// drain_matching!(data, Some(x) if x > 1 if x < 5 => x);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! drain_matching {
+    ($vec:expr, $($pat:pat)|+ => $out:expr) => ({
+        use $crate::VecDrainWhereExt;
+        ($vec).e_extract_map(|item| match item {
+            $($pat)|+ => Ok($out),
+            other => Err(other)
+        })
+    });
+    ($vec:expr, $($pat:pat)|+ if $guard:expr => $out:expr) => ({
+        use $crate::VecDrainWhereExt;
+        ($vec).e_extract_map(|item| match item {
+            $($pat)|+ if $guard => Ok($out),
+            other => Err(other)
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Timeout(u32),
+        Data(String),
+        Close
+    }
+
+    #[test]
+    fn extract_variant_fields() {
+        let mut events = vec![
+            Event::Data("a".to_owned()),
+            Event::Timeout(1),
+            Event::Close,
+            Event::Timeout(2),
+            Event::Data("b".to_owned())
+        ];
+        let timeouts = events.e_extract_map(|ev| match ev {
+            Event::Timeout(t) => Ok(t),
+            other => Err(other)
+        }).collect::<Vec<_>>();
+        assert_eq!(timeouts, vec![1, 2]);
+        assert_eq!(events, vec![
+            Event::Data("a".to_owned()),
+            Event::Close,
+            Event::Data("b".to_owned())
+        ]);
+    }
+
+    fn extract_with_mask(mask: Vec<bool>, dirs: Vec<bool>, panic_at: Option<usize>) -> TestResult {
+        let counter = Rc::new(());
+        let mut data = (0..mask.len()).map(|idx| (idx, counter.clone())).collect::<Vec<_>>();
+        let mut extracted = Vec::new();
+        let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            let mut iter = data.e_extract_map(|(idx, rc)| {
+                if Some(idx) == panic_at {
+                    panic!("-- yes panic --");
+                }
+                if mask[idx] { Ok(idx) } else { Err((idx, rc)) }
+            });
+            let mut dirs = dirs.iter().cloned().chain(::std::iter::repeat(false));
+            loop {
+                let next = if dirs.next().unwrap() { iter.next_back() } else { iter.next() };
+                match next {
+                    Some(idx) => extracted.push(idx),
+                    None => break
+                }
+            }
+        }));
+        let remaining = data.iter().map(|&(idx, _)| idx).collect::<Vec<_>>();
+        if remaining.windows(2).any(|w| w[0] >= w[1]) {
+            return TestResult::error(format!("order not kept {:?}", remaining));
+        }
+        if extracted.iter().any(|idx| !mask[*idx]) {
+            return TestResult::error(format!("extracted unmasked element {:?}", extracted));
+        }
+        let panicked = panic_at.map(|idx| idx < mask.len()).unwrap_or(false);
+        if res.is_err() != panicked {
+            return TestResult::error("unexpected panic result");
+        }
+        if !panicked && remaining != (0..mask.len()).filter(|idx| !mask[*idx]).collect::<Vec<_>>() {
+            return TestResult::error(format!("remaining {:?} ({:?})", remaining, mask));
+        }
+        drop(data);
+        if Rc::strong_count(&counter) != 1 {
+            return TestResult::error("leaked elements");
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_extract_with_mask() {
+        ::quickcheck::quickcheck(extract_with_mask as fn(Vec<bool>, Vec<bool>, Option<usize>) -> TestResult);
+    }
+
+    #[test]
+    fn drain_matching_with_alternatives_and_guard() {
+        let mut events = vec![
+            Event::Timeout(1),
+            Event::Close,
+            Event::Timeout(5),
+            Event::Data("a".to_owned()),
+            Event::Timeout(7)
+        ];
+        {
+            let events = &mut events;
+            let drained = drain_matching!(events, Event::Timeout(t) if t > 3 => t)
+                .collect::<Vec<_>>();
+            assert_eq!(drained, vec![5, 7]);
+        }
+        let drained = drain_matching!(events, Event::Close | Event::Data(_) => ())
+            .count();
+        assert_eq!(drained, 2);
+        assert_eq!(events, vec![Event::Timeout(1)]);
+    }
+
+}
@@ -0,0 +1,126 @@
+//! Drain iterator with a fallible predicate.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use {OnPanic, Decision};
+
+/// Iterator for draining a vector with a predicate which can fail.
+///
+/// See `VecDrainWhereExt::try_e_drain_where`.
+#[must_use]
+#[derive(Debug)]
+pub struct TryVecDrainWhere<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred
+}
+
+impl<'a, I: 'a, P> TryVecDrainWhere<'a, I, P> {
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        TryVecDrainWhere { raw, predicate }
+    }
+}
+
+/// Maps the result of the fallible predicate to a decision, moving
+/// the error (if any) into `error` and stopping in that case.
+///
+/// The element the predicate failed on is kept (at its position).
+fn decide<I, P, E>(predicate: &mut P, item: &mut I, error: &mut Option<E>) -> Decision
+    where P: FnMut(&mut I) -> Result<bool, E>
+{
+    match predicate(item) {
+        Ok(drain) => Decision::from(drain),
+        Err(err) => {
+            *error = Some(err);
+            Decision::KeepAndStop
+        }
+    }
+}
+
+impl<'a, I: 'a, P, E> Iterator for TryVecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> Result<bool, E>
+{
+    type Item = Result<I, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        let mut error = None;
+        let drained = self.raw.next_front(OnPanic::Leak, |_, item| decide(predicate, item, &mut error));
+        match drained {
+            Some((_, item)) => Some(Ok(item)),
+            None => error.map(Err)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // each visited element results in at most one item or error
+        (0, Some(self.raw.upper_bound()))
+    }
+}
+
+impl<'a, I: 'a, P, E> DoubleEndedIterator for TryVecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> Result<bool, E>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        let mut error = None;
+        let drained = self.raw.next_back(OnPanic::Leak, |_, item| decide(predicate, item, &mut error));
+        match drained {
+            Some((_, item)) => Some(Ok(item)),
+            None => error.map(Err)
+        }
+    }
+}
+
+impl<'a, I: 'a, P, E> FusedIterator for TryVecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> Result<bool, E>
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn fail_with_mask(mask: Vec<(bool, bool)>) -> TestResult {
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let results = data.try_e_drain_where(|el| {
+            let (drain, fail) = mask[*el];
+            if fail { Err(*el) } else { Ok(drain) }
+        }).collect::<Vec<_>>();
+
+        let fail_at = mask.iter().position(|&(_, fail)| fail);
+        let mut expected_results = (0..fail_at.unwrap_or(mask.len()))
+            .filter(|el| mask[*el].0)
+            .map(Ok)
+            .collect::<Vec<_>>();
+        if let Some(fail_at) = fail_at {
+            expected_results.push(Err(fail_at));
+        }
+        if results != expected_results {
+            return TestResult::error(format!("results {:?}, exp {:?}", results, expected_results));
+        }
+        let expected = (0..mask.len())
+            .filter(|el| fail_at.map(|f| *el >= f).unwrap_or(false) || !mask[*el].0)
+            .collect::<Vec<_>>();
+        if data != expected {
+            return TestResult::error(format!("remaining {:?}, exp {:?}", data, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_fail_with_mask() {
+        ::quickcheck::quickcheck(fail_with_mask as fn(Vec<(bool, bool)>) -> TestResult);
+    }
+
+    #[test]
+    fn stops_on_first_error() {
+        let mut data = vec!["1", "x", "3", "y"];
+        let mut iter = data.try_e_drain_where(|s| s.parse::<u8>().map(|n| n % 2 == 1));
+        assert_eq!(iter.next(), Some(Ok("1")));
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        drop(iter);
+        assert_eq!(data, vec!["x", "3", "y"]);
+    }
+}
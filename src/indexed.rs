@@ -0,0 +1,100 @@
+//! Drain iterator passing the original index of elements to the predicate.
+use std::iter::FusedIterator;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// Iterator for draining a vector conditionally, yielding the drained
+/// elements together with their original index.
+///
+/// See `VecDrainWhereExt::e_drain_where_indexed`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecDrainWhereIndexed<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred
+}
+
+impl<'a, I: 'a, P> VecDrainWhereIndexed<'a, I, P>
+    where P: FnMut(usize, &mut I) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        VecDrainWhereIndexed { raw, predicate }
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecDrainWhereIndexed<'a, I, P>
+    where P: FnMut(usize, &mut I) -> bool
+{
+    type Item = (usize, I);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next_front(OnPanic::Leak, &mut self.predicate)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.upper_bound()))
+    }
+}
+
+impl<'a, I: 'a, P> DoubleEndedIterator for VecDrainWhereIndexed<'a, I, P>
+    where P: FnMut(usize, &mut I) -> bool
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.next_back(OnPanic::Leak, &mut self.predicate)
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecDrainWhereIndexed<'a, I, P>
+    where P: FnMut(usize, &mut I) -> bool
+{}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn indices_with_mask(mask: Vec<bool>, from_back: bool) -> TestResult {
+        let mut data = (0..mask.len()).map(|idx| idx * 10).collect::<Vec<_>>();
+        let mut visited = Vec::new();
+        let mut drained = {
+            let iter = data.e_drain_where_indexed(|idx, el| {
+                visited.push(idx);
+                *el == idx * 10 && mask[idx]
+            });
+            if from_back {
+                iter.rev().collect::<Vec<_>>()
+            } else {
+                iter.collect::<Vec<_>>()
+            }
+        };
+        if from_back {
+            visited.reverse();
+            drained.reverse();
+        }
+
+        if visited != (0..mask.len()).collect::<Vec<_>>() {
+            return TestResult::error(format!("unexpected visiting order {:?}", visited));
+        }
+        let expected = (0..mask.len()).filter(|idx| mask[*idx])
+            .map(|idx| (idx, idx * 10))
+            .collect::<Vec<_>>();
+        if drained != expected {
+            return TestResult::error(format!("drained {:?}, exp {:?}", drained, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_indices_with_mask() {
+        ::quickcheck::quickcheck(indices_with_mask as fn(Vec<bool>, bool) -> TestResult);
+    }
+
+    #[test]
+    fn indices_are_original_positions() {
+        let mut data = vec!['a', 'b', 'c', 'd', 'e'];
+        let drained = data.e_drain_where_indexed(|_, c| *c != 'c').collect::<Vec<_>>();
+        assert_eq!(drained, vec![(0, 'a'), (1, 'b'), (3, 'd'), (4, 'e')]);
+        assert_eq!(data, vec!['c']);
+    }
+}
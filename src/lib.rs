@@ -12,7 +12,40 @@
 #[cfg(test)]
 extern crate quickcheck;
 
-use std::{isize, ptr, mem};
+use std::{thread, ptr};
+use std::mem::ManuallyDrop;
+use std::iter::FusedIterator;
+use std::ops::RangeBounds;
+
+mod raw;
+mod indexed;
+mod decide;
+mod fallible;
+mod extract;
+mod bounded;
+mod cursor;
+mod candidate;
+mod splice;
+mod context;
+mod adjacent;
+mod runs;
+mod sink;
+mod partition;
+
+pub mod pred;
+
+use raw::RawDrain;
+
+pub use indexed::VecDrainWhereIndexed;
+pub use decide::{Decision, VecDrainDecide};
+pub use fallible::TryVecDrainWhere;
+pub use extract::VecExtractMap;
+pub use bounded::VecDrainWhereMax;
+pub use cursor::DrainCursor;
+pub use candidate::{DrainCandidates, DrainCandidate};
+pub use context::{DrainContext, VecDrainWhereCtx};
+pub use adjacent::{VecCoalesceWhere, VecCancelPairs};
+pub use runs::{VecDrainRunsWhere, VecSplitWhere};
 
 /// Ext. trait adding `e_drain_where` to `Vec`
 pub trait VecDrainWhereExt<Item> {
@@ -24,8 +57,22 @@ pub trait VecDrainWhereExt<Item> {
     /// will stop draining once short circuiting is hit. So use it
     /// with care.
     ///
+    /// This can be changed per call through `with_drop_policy`,
+    /// e.g. `with_drop_policy(OnDrop::DropMatching)` makes it
+    /// run to completion on drop like `drain_filter` does.
+    ///
+    /// With the `strict` feature dropping the iterator before
+    /// all elements where visited panics in debug builds, unless
+    /// it was explicitly stopped (`keep_rest`/`finish`/`drain_rest`)
+    /// or a drop policy other then `OnDrop::KeepRest` is used. Use
+    /// `by_ref()` to apply short circuiting combinators in that case,
+    /// e.g. `iter.by_ref().take(n).for_each(f); iter.keep_rest();`.
+    ///
     /// you can use fold e.g. `any(pred)` => `fold(false, |s| )
     ///
+    /// Once the iterator returned `None` it will keep returning
+    /// `None` (it is a `FusedIterator`).
+    ///
     /// # Leak Behavior
     ///
     /// For safety reasons the length of the original vector
@@ -35,6 +82,7 @@ pub trait VecDrainWhereExt<Item> {
     ///
     /// When the iterator is dropped due to an panic in
     /// the predicate the element it panicked on is leaked
+    /// (this can be changed through `with_panic_policy`)
     /// but all elements which have already been decided
     /// to not be drained and such which have not yet been
     /// decided about will still be in the vector safely.
@@ -56,56 +104,583 @@ pub trait VecDrainWhereExt<Item> {
     /// in the normal case replace the `|` with `||`
     /// and the `&` with `&&`.
     fn e_drain_where<F>(&mut self, predicate: F)
-        -> VecDrainWhere<Item, F>
+        -> VecDrainWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool;
+
+    /// Like `e_drain_where` but only considers elements in given range.
+    ///
+    /// Elements outside of the range are neither passed to the
+    /// predicate nor moved, except for shifting the elements after
+    /// the range to close the gap left by drained elements.
+    ///
+    /// Iterating from the back (`next_back`) starts at the end
+    /// of the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end
+    /// or if the end of the range is greater than the length of
+    /// the vector.
+    fn e_drain_where_range<R, F>(&mut self, range: R, predicate: F)
+        -> VecDrainWhere<'_, Item, F>
+        where R: RangeBounds<usize>, F: FnMut(&mut Item) -> bool;
+
+    /// Like `e_drain_where` but passes the index of the element to the predicate.
+    ///
+    /// The index is the position the element had in the vector when
+    /// draining started, drained elements are returned together with it.
+    ///
+    /// The iterator keeps the elements not yet visited when dropped
+    /// and leaks the element the predicate panicked on (like the
+    /// defaults of `e_drain_where`).
+    fn e_drain_where_indexed<F>(&mut self, predicate: F)
+        -> VecDrainWhereIndexed<'_, Item, F>
+        where F: FnMut(usize, &mut Item) -> bool;
+
+    /// Like `e_drain_where` but the predicate can decide to stop draining.
+    ///
+    /// Once the predicate returned `Decision::KeepAndStop` or
+    /// `Decision::DrainAndStop` no further elements are visited and
+    /// all elements not yet visited are kept. This makes the stop
+    /// deterministic instead of depending on when the iterator is
+    /// dropped.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element the predicate
+    /// panicked on is leaked.
+    fn e_drain_where_decide<F>(&mut self, predicate: F)
+        -> VecDrainDecide<'_, Item, F>
+        where F: FnMut(&mut Item) -> Decision;
+
+    /// Like `e_drain_where` but with a predicate which can fail.
+    ///
+    /// Drained elements are returned as `Ok(item)`. The first error
+    /// returned by the predicate is returned from the iterator and
+    /// stops the draining, the element the predicate failed on and
+    /// all elements not yet visited are kept in the vector.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element the predicate
+    /// panicked on is leaked.
+    fn try_e_drain_where<F, E>(&mut self, predicate: F)
+        -> TryVecDrainWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> Result<bool, E>;
+
+    /// Extracts elements by value, mapping them to an output.
+    ///
+    /// The closure takes each element by value and either returns
+    /// `Ok(out)` to drain it, in which case the iterator yields `out`,
+    /// or `Err(item)` to put it back at its place in the vector.
+    ///
+    /// This allows moving data out of the drained elements without
+    /// needing a placeholder value, e.g. `Event::Timeout(t) => Ok(t)`.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps all
+    /// elements not yet visited. If the closure panics the element it
+    /// was called with is owned by it, i.e. it is dropped during
+    /// unwinding and not leaked.
+    fn e_extract_map<F, Out>(&mut self, func: F)
+        -> VecExtractMap<'_, Item, F>
+        where F: FnMut(Item) -> Result<Out, Item>;
+
+    /// Like `e_drain_where` but drains at most `max` elements.
+    ///
+    /// Once `max` elements are drained the predicate is no longer
+    /// called and all elements not yet visited are kept (and moved
+    /// in one go when the iterator is dropped).
+    ///
+    /// In difference to `e_drain_where(pred).take(max)` this
+    /// doesn't rely on the iterator being dropped early.
+    fn e_drain_where_max<F>(&mut self, max: usize, predicate: F)
+        -> VecDrainWhereMax<'_, Item, F>
         where F: FnMut(&mut Item) -> bool;
+
+    /// Returns a cursor to manually edit the vector in a single pass.
+    ///
+    /// The cursor starts at the first element, elements can be kept,
+    /// removed or replaced, which advances the cursor. Elements not
+    /// yet visited when the cursor is dropped are kept.
+    ///
+    /// For safety reasons the length of the vector is set to 0
+    /// while the cursor lives.
+    fn e_drain_cursor(&mut self) -> DrainCursor<'_, Item>;
+
+    /// Yields each element as a `DrainCandidate` instead of calling a predicate.
+    ///
+    /// The candidate dereferences to the element, calling `take` on
+    /// it drains the element, dropping it keeps the element. As the
+    /// decision is made by the caller it can use `?`, `.await` or
+    /// borrow from the surrounding scope.
+    ///
+    /// Elements not yet visited when `DrainCandidates` is dropped
+    /// are kept.
+    fn e_drain_candidates(&mut self) -> DrainCandidates<'_, Item>;
+
+    /// Replaces each element the predicate is true for with the elements
+    /// returned by `replace_with`, keeping the order.
+    ///
+    /// `replace_with` can return zero or more elements. They are written
+    /// into the gap left by previously removed elements where possible,
+    /// only elements for which there is no space are buffered and
+    /// inserted at the end. Returns the number of replaced elements.
+    ///
+    /// If the predicate panics the element it panicked on is leaked (like
+    /// with `e_drain_where`), if `replace_with` panics the element is
+    /// dropped by it. In both cases all other elements (including already
+    /// returned replacements) are kept in the vector.
+    fn e_splice_where<F, R, Rep>(&mut self, predicate: F, replace_with: R) -> usize
+        where F: FnMut(&mut Item) -> bool,
+              R: FnMut(Item) -> Rep,
+              Rep: IntoIterator<Item=Item>;
+
+    /// Like `e_drain_where` but the predicate gets a `DrainContext`.
+    ///
+    /// Besides the current element the context gives access to the
+    /// elements kept so far (`kept`), the elements not yet visited
+    /// (`rest`) and the number of elements drained and kept so far.
+    /// This allows rules like "drain if equal to the last kept element"
+    /// or "drain if a newer entry with the same id follows".
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element the predicate
+    /// panicked on is leaked.
+    fn e_drain_where_ctx<F>(&mut self, predicate: F)
+        -> VecDrainWhereCtx<'_, Item, F>
+        where F: FnMut(DrainContext<'_, Item>) -> bool;
+
+    /// Merges elements into the last kept element, draining the merged elements.
+    ///
+    /// `merge` is called with the last kept element and the current
+    /// element, if it returns true it merged the current element into
+    /// the last kept one and the current element is drained (yielded
+    /// by the iterator). The first element is always kept.
+    ///
+    /// This is a generalization of `Vec::dedup_by` which allows
+    /// e.g. joining adjacent text spans or overlapping intervals.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element `merge` panicked
+    /// on is leaked.
+    fn e_coalesce_where<F>(&mut self, merge: F)
+        -> VecCoalesceWhere<'_, Item, F>
+        where F: FnMut(&mut Item, &mut Item) -> bool;
+
+    /// Removes pairs of adjacent elements which cancel each other.
+    ///
+    /// The kept elements are treated as a stack: `cancels` is called
+    /// with the last kept element and the current element, if it
+    /// returns true both are drained and yielded as `(last, current)`
+    /// pair. The next element is then compared with the element kept
+    /// before the removed one, e.g. `[Open, Open, Close, Close]` is
+    /// fully removed with the pairs yielded inner to outer.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element `cancels` panicked
+    /// on is leaked.
+    fn e_cancel_pairs_where<F>(&mut self, cancels: F)
+        -> VecCancelPairs<'_, Item, F>
+        where F: FnMut(&mut Item, &mut Item) -> bool;
+
+    /// Drains maximal runs of consecutive elements the predicate is true for.
+    ///
+    /// Each run is yielded as a `Vec`. The predicate is called once per
+    /// element, a run ends at the first element it returns false for
+    /// (which is kept) or at the end of the vector.
+    ///
+    /// Like with `e_drain_where` dropping the iterator early keeps
+    /// all elements not yet visited and the element the predicate
+    /// panicked on is leaked. If the predicate panics while a run is
+    /// collected the already collected part of the run is dropped.
+    fn e_drain_runs_where<F>(&mut self, predicate: F)
+        -> VecDrainRunsWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool;
+
+    /// Like `e_drain_runs_where` but passes each run as slice to `on_run`.
+    ///
+    /// The run is passed while it's still in the buffer of the vector
+    /// and is dropped afterwards, so no allocation is needed. Returns
+    /// the number of runs.
+    ///
+    /// If `on_run` panics the run is dropped, too.
+    fn e_drain_runs_where_with<F, R>(&mut self, predicate: F, on_run: R) -> usize
+        where F: FnMut(&mut Item) -> bool, R: FnMut(&mut [Item]);
+
+    /// Drains the vector as segments separated by elements the predicate is true for.
+    ///
+    /// The separating elements are dropped. Like `slice::split` this
+    /// yields `n + 1` segments for `n` separators, i.e. it yields
+    /// empty segments for adjacent separators and for separators at
+    /// the start/end, and a single empty segment for an empty vector.
+    ///
+    /// If run to completion the vector is empty afterwards (but keeps
+    /// its capacity), if dropped early all elements not yet visited
+    /// are kept.
+    ///
+    /// If the predicate panics the element it panicked on is leaked and
+    /// all elements not yet yielded (including the partial segment
+    /// before that element) are kept.
+    fn e_split_where<F>(&mut self, predicate: F)
+        -> VecSplitWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool;
+
+    /// Moves all elements the predicate is true for into `target`.
+    ///
+    /// Returns the number of moved elements. This is the same as
+    /// `target.extend(vec.e_drain_where(predicate))` including the
+    /// behaviour on panics. For `Vec` targets `e_move_where_into`
+    /// is faster.
+    fn e_move_where<E, F>(&mut self, target: &mut E, predicate: F) -> usize
+        where E: Extend<Item>, F: FnMut(&mut Item) -> bool;
+
+    /// Moves all elements the predicate is true for to the end of `target`.
+    ///
+    /// Returns the number of moved elements. Runs of consecutive matching
+    /// elements are moved with a single copy instead of one element at
+    /// a time.
+    ///
+    /// If the predicate panics all elements for which it returned true
+    /// have already been moved to `target` and the element it panicked
+    /// on is leaked, like with `e_drain_where`.
+    fn e_move_where_into<F>(&mut self, target: &mut Vec<Item>, predicate: F) -> usize
+        where F: FnMut(&mut Item) -> bool;
+
+    /// Moves elements into one of multiple buckets in a single pass.
+    ///
+    /// If the classifier returns `Some(idx)` the element is pushed to
+    /// `buckets[idx]`, if it returns `None` the element is kept. Returns
+    /// the number of moved elements.
+    ///
+    /// If the classifier panics (or returns an index out of range, which
+    /// panics) all elements already moved stay in their buckets, the
+    /// element it panicked on is leaked and all elements not yet
+    /// visited are kept, like with `e_drain_where`.
+    fn e_partition_into<C>(&mut self, classifier: C, buckets: &mut [Vec<Item>]) -> usize
+        where C: FnMut(&mut Item) -> Option<usize>;
 }
 
 impl<Item> VecDrainWhereExt<Item> for Vec<Item> {
     fn e_drain_where<F>(&mut self, predicate: F)
-        -> VecDrainWhere<Item, F>
+        -> VecDrainWhere<'_, Item, F>
         where F: FnMut(&mut Item) -> bool
     {
-        let ptr = self.as_mut_ptr();
-        let len = self.len();
-        if len == 0 {
-            let nptr = 0 as *mut _;
-            return VecDrainWhere {
-                pos: nptr,
-                gap_pos: nptr,
-                end: nptr,
-                self_ref: self,
-                predicate
-            };
-        }
+        self.e_drain_where_range(.., predicate)
+    }
 
-        if len > isize::MAX as usize {
-            panic!("can not handle more then isize::MAX elements");
+    fn e_drain_where_range<R, F>(&mut self, range: R, predicate: F)
+        -> VecDrainWhere<'_, Item, F>
+        where R: RangeBounds<usize>, F: FnMut(&mut Item) -> bool
+    {
+        VecDrainWhere {
+            raw: RawDrain::new(self, range),
+            predicate,
+            drop_matching: VecDrainWhere::drop_matching,
+            on_drop: OnDrop::default(),
+            on_panic: OnPanic::default(),
+            stopped_explicitly: false,
+            peeked: false
         }
+    }
+
+    fn e_drain_where_indexed<F>(&mut self, predicate: F)
+        -> VecDrainWhereIndexed<'_, Item, F>
+        where F: FnMut(usize, &mut Item) -> bool
+    {
+        VecDrainWhereIndexed::new(RawDrain::new(self, ..), predicate)
+    }
 
-        // leak amplification for safety
-        unsafe { self.set_len(0) }
+    fn e_drain_where_decide<F>(&mut self, predicate: F)
+        -> VecDrainDecide<'_, Item, F>
+        where F: FnMut(&mut Item) -> Decision
+    {
+        VecDrainDecide::new(RawDrain::new(self, ..), predicate)
+    }
 
-        let end = unsafe { ptr.offset(len as isize) };
+    fn try_e_drain_where<F, E>(&mut self, predicate: F)
+        -> TryVecDrainWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> Result<bool, E>
+    {
+        TryVecDrainWhere::new(RawDrain::new(self, ..), predicate)
+    }
 
-        VecDrainWhere {
-            pos: ptr,
-            gap_pos: ptr,
-            end,
-            self_ref: self,
-            predicate
-        }
+    fn e_extract_map<F, Out>(&mut self, func: F)
+        -> VecExtractMap<'_, Item, F>
+        where F: FnMut(Item) -> Result<Out, Item>
+    {
+        VecExtractMap::new(RawDrain::new(self, ..), func)
+    }
+
+    fn e_drain_where_max<F>(&mut self, max: usize, predicate: F)
+        -> VecDrainWhereMax<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool
+    {
+        VecDrainWhereMax::new(RawDrain::new(self, ..), max, predicate)
+    }
+
+    fn e_drain_cursor(&mut self) -> DrainCursor<'_, Item> {
+        DrainCursor::new(RawDrain::new(self, ..))
+    }
+
+    fn e_drain_candidates(&mut self) -> DrainCandidates<'_, Item> {
+        DrainCandidates::new(RawDrain::new(self, ..))
+    }
+
+    fn e_splice_where<F, R, Rep>(&mut self, predicate: F, replace_with: R) -> usize
+        where F: FnMut(&mut Item) -> bool,
+              R: FnMut(Item) -> Rep,
+              Rep: IntoIterator<Item=Item>
+    {
+        splice::splice_where(self, predicate, replace_with)
+    }
+
+    fn e_drain_where_ctx<F>(&mut self, predicate: F)
+        -> VecDrainWhereCtx<'_, Item, F>
+        where F: FnMut(DrainContext<'_, Item>) -> bool
+    {
+        VecDrainWhereCtx::new(RawDrain::new(self, ..), predicate)
+    }
+
+    fn e_coalesce_where<F>(&mut self, merge: F)
+        -> VecCoalesceWhere<'_, Item, F>
+        where F: FnMut(&mut Item, &mut Item) -> bool
+    {
+        VecCoalesceWhere::new(RawDrain::new(self, ..), merge)
+    }
+
+    fn e_cancel_pairs_where<F>(&mut self, cancels: F)
+        -> VecCancelPairs<'_, Item, F>
+        where F: FnMut(&mut Item, &mut Item) -> bool
+    {
+        VecCancelPairs::new(RawDrain::new(self, ..), cancels)
+    }
+
+    fn e_drain_runs_where<F>(&mut self, predicate: F)
+        -> VecDrainRunsWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool
+    {
+        VecDrainRunsWhere::new(RawDrain::new(self, ..), predicate)
+    }
+
+    fn e_drain_runs_where_with<F, R>(&mut self, predicate: F, on_run: R) -> usize
+        where F: FnMut(&mut Item) -> bool, R: FnMut(&mut [Item])
+    {
+        runs::drain_runs_with(self, predicate, on_run)
+    }
+
+    fn e_split_where<F>(&mut self, predicate: F)
+        -> VecSplitWhere<'_, Item, F>
+        where F: FnMut(&mut Item) -> bool
+    {
+        VecSplitWhere::new(RawDrain::new(self, ..), predicate)
+    }
+
+    fn e_move_where<E, F>(&mut self, target: &mut E, predicate: F) -> usize
+        where E: Extend<Item>, F: FnMut(&mut Item) -> bool
+    {
+        let mut moved = 0;
+        target.extend(self.e_drain_where(predicate).inspect(|_| moved += 1));
+        moved
+    }
+
+    fn e_move_where_into<F>(&mut self, target: &mut Vec<Item>, predicate: F) -> usize
+        where F: FnMut(&mut Item) -> bool
+    {
+        sink::move_where_into(self, target, predicate)
+    }
+
+    fn e_partition_into<C>(&mut self, classifier: C, buckets: &mut [Vec<Item>]) -> usize
+        where C: FnMut(&mut Item) -> Option<usize>
+    {
+        partition::partition_into(self, classifier, buckets)
     }
 }
 
+/// What to do with the elements not yet visited when a `VecDrainWhere` is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDrop {
+    /// Keep all elements not yet visited in the vector (the default).
+    ///
+    /// This allows stopping the draining from the outside.
+    KeepRest,
+
+    /// Run the predicate to completion and drop all elements it returns
+    /// true for, i.e. behave like the std's `drain_filter`.
+    DropMatching,
+
+    /// Drop all elements not yet visited without calling the predicate.
+    DrainAll
+}
+
+// not derived, as `#[default]` needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for OnDrop {
+    fn default() -> Self {
+        OnDrop::KeepRest
+    }
+}
+
+/// What to do with the element the predicate panicked on.
+///
+/// Elements which have already been decided about and elements
+/// not yet visited are kept in the vector independent of this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnPanic {
+    /// Leak the element (the default).
+    ///
+    /// This is always safe, as the predicate might have left the
+    /// element in an inconsistent state, which could make it e.g.
+    /// panic on drop (through it surly shouldn't behave this way).
+    Leak,
+
+    /// Keep the element in the vector, at its original position.
+    ///
+    /// This is safe as the predicate only gets a `&mut` reference,
+    /// so the element is still a valid value. But it's up to the
+    /// caller to make sure the predicate doesn't leave it in a
+    /// state which is problematic for later uses of the vector.
+    Keep,
+
+    /// Drop the element while unwinding.
+    ///
+    /// This is safe as the element is removed from the vector
+    /// before it is dropped, but if dropping it panics, too, the
+    /// process is aborted (panic while panicking).
+    Drop
+}
+
+// not derived, as `#[default]` needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for OnPanic {
+    fn default() -> Self {
+        OnPanic::Leak
+    }
+}
+
+/// Counts of elements handled by a drain, see `VecDrainWhere::finish`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrainReport {
+    /// Number of elements removed from the vector.
+    ///
+    /// This includes an element leaked/dropped because
+    /// the predicate panicked on it.
+    pub drained: usize,
+
+    /// Number of elements visited and kept in the vector.
+    pub kept: usize,
+
+    /// Number of elements not visited (and kept in the vector).
+    pub unvisited: usize
+}
+
 /// Iterator for draining a vector conditionally.
 #[must_use]
 #[derive(Debug)]
 pub struct VecDrainWhere<'a, Item: 'a, Pred> {
-    pos: *mut Item,
-    gap_pos: *mut Item,
-    end: *mut Item,
+    raw: RawDrain<'a, Item>,
     predicate: Pred,
-    self_ref: &'a mut Vec<Item>
+    /// Runs the drain to completion, dropping the drained elements.
+    ///
+    /// Set on construction (where the predicate is known to be
+    /// callable) so that `Drop` doesn't need a bound on `Pred`.
+    drop_matching: fn(&mut VecDrainWhere<'a, Item, Pred>),
+    on_drop: OnDrop,
+    on_panic: OnPanic,
+    stopped_explicitly: bool,
+    /// The first unvisited element is known to be drained next (see `peek_mut`).
+    peeked: bool
+}
+
+impl<'a, I: 'a, P> VecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+
+    /// Sets what happens with the elements not yet visited when dropped.
+    ///
+    /// By default they are kept (`OnDrop::KeepRest`).
+    pub fn with_drop_policy(mut self, policy: OnDrop) -> Self {
+        self.on_drop = policy;
+        self
+    }
+
+    /// Sets what happens with the element the predicate panicked on.
+    ///
+    /// By default it is leaked (`OnPanic::Leak`).
+    pub fn with_panic_policy(mut self, policy: OnPanic) -> Self {
+        self.on_panic = policy;
+        self
+    }
+
+    /// Returns the element which will be drained next, without draining it.
+    ///
+    /// This calls the predicate on the elements not yet visited until it
+    /// returns true, the element is then left in the vector until `next`
+    /// is called. I.e. if the draining is stopped after peeking (e.g. by
+    /// dropping the iterator or `keep_rest`) the element is kept in the
+    /// vector at its original position (including any changes done to it
+    /// through the returned reference). The predicate is not called on it
+    /// again.
+    pub fn peek_mut(&mut self) -> Option<&mut I> {
+        if !self.peeked {
+            let predicate = &mut self.predicate;
+            self.peeked = self.raw.seek_front(self.on_panic, |_, item| predicate(item));
+        }
+        if self.peeked {
+            self.raw.front_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Stops draining, keeping all elements not yet visited.
+    ///
+    /// This ignores the drop policy and is the same as dropping
+    /// the iterator with `OnDrop::KeepRest`, but makes it explicit
+    /// that stopping here is intended.
+    pub fn keep_rest(mut self) -> DrainReport {
+        self.on_drop = OnDrop::KeepRest;
+        self.stopped_explicitly = true;
+        self.raw.report()
+    }
+
+    /// Stops calling the predicate and drains all elements not yet visited.
+    ///
+    /// Elements not consumed from the returned iterator are dropped
+    /// when it is dropped.
+    pub fn drain_rest(self) -> DrainRest<'a, I> {
+        let this = ManuallyDrop::new(self);
+        // the raw drain is moved out, the predicate dropped
+        // and the drop policy no longer applies
+        let raw = unsafe { ptr::read(&this.raw) };
+        drop(unsafe { ptr::read(&this.predicate) });
+        DrainRest { raw }
+    }
+
+    /// Finishes draining by applying the drop policy and returns
+    /// how many elements where drained, kept and not visited.
+    ///
+    /// This is the same as dropping the iterator, but makes it
+    /// explicit that stopping here is intended.
+    pub fn finish(mut self) -> DrainReport {
+        self.stopped_explicitly = true;
+        self.apply_drop_policy();
+        self.raw.report()
+    }
+
+    fn drop_matching(&mut self) {
+        for item in self {
+            drop(item);
+        }
+    }
+}
+
+impl<'a, I: 'a, P> VecDrainWhere<'a, I, P> {
+
+    fn apply_drop_policy(&mut self) {
+        match self.on_drop {
+            OnDrop::KeepRest => {},
+            OnDrop::DropMatching => (self.drop_matching)(self),
+            OnDrop::DrainAll => self.raw.drop_rest()
+        }
+    }
 }
 
 impl<'a, I: 'a, P> Iterator for VecDrainWhere<'a, I, P>
@@ -114,68 +689,133 @@ impl<'a, I: 'a, P> Iterator for VecDrainWhere<'a, I, P>
     type Item = I;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if self.pos.is_null() || self.pos >= self.end {
-                return None;
-            } else {
-                unsafe {
-                    let ref_to_current = &mut *self.pos;
-                    self.pos = self.pos.offset(1);
-                    let should_be_drained = (self.predicate)(ref_to_current);
-                    if should_be_drained {
-                        let item = ptr::read(ref_to_current);
-                        return Some(item);
-                    } else {
-                        if self.gap_pos < ref_to_current {
-                            ptr::copy_nonoverlapping(ref_to_current, self.gap_pos, 1);
-                        }
-                        self.gap_pos = self.gap_pos.offset(1);
-                    }
-                }
-            }
+        if self.peeked {
+            self.peeked = false;
+            return self.raw.take_front();
         }
+        let predicate = &mut self.predicate;
+        self.raw.next_front(self.on_panic, |_, item| predicate(item))
+            .map(|(_, item)| item)
     }
 
+    /// The upper bound is the number of elements not yet
+    /// visited by the predicate.
+    ///
+    /// (The length of the vector can not be used for this as it
+    /// is set to 0 while the drain iterator lives.)
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(self.self_ref.len()))
+        let lower = if self.peeked { 1 } else { 0 };
+        (lower, Some(self.raw.upper_bound()))
+    }
+}
+
+impl<'a, I: 'a, P> DoubleEndedIterator for VecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    /// Drains elements starting from the back of the vector.
+    ///
+    /// Elements kept when iterating from the back are moved
+    /// to a second gap at the end of the vector, which is
+    /// closed together with the front gap on drop.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // the predicate already decided to drain the peeked element
+        let peeked_idx = if self.peeked { Some(self.raw.front_idx()) } else { None };
+        let predicate = &mut self.predicate;
+        let (idx, item) = self.raw.next_back(self.on_panic, |idx, item| {
+            Some(idx) == peeked_idx || predicate(item)
+        })?;
+        if Some(idx) == peeked_idx {
+            self.peeked = false;
+        }
+        Some(item)
     }
 }
 
+impl<'a, I: 'a, P> FusedIterator for VecDrainWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{}
+
 impl<'a, I: 'a, P> Drop for VecDrainWhere<'a, I, P> {
     /// If the iterator was run to completion this will
     /// set the len to the new len after drop. I.e. it
     /// will undo the leak amplification.
     ///
     /// If the iterator is dropped before completion this
-    /// will move the remaining elements to the (single)
-    /// gap (still) left from draining elements and then
-    /// sets the new length.
+    /// will handle the remaining elements as specified by
+    /// the drop policy (see `with_drop_policy`), move the
+    /// elements which are kept to the gap (still) left from
+    /// draining elements and then sets the new length.
+    ///
+    /// If the iterator is dropped because of a panic the
+    /// remaining elements are always kept, independent of
+    /// the drop policy, to not risk a double panic.
     ///
     /// If the iterator is dropped because the called
     /// predicate panicked the element it panicked on
-    /// is _leaked_. This is because its simply to easy
-    /// to leaf the `&mut T` value in a illegal state
+    /// is by default _leaked_. This is because its simply
+    /// to easy to leaf the `&mut T` value in a illegal state
     /// likely to panic drop or even behave unsafely
     /// (through it surly shouldn't behave this way).
+    /// This can be changed through `with_panic_policy`.
     fn drop(&mut self) {
-        let pos = self.pos as usize;
-        if self.pos.is_null() {
-            return
+        if thread::panicking() {
+            return;
         }
-        let start  = self.self_ref.as_mut_ptr() as usize;
-        let end = self.end as usize;
-        let gap = self.gap_pos as usize;
-        let item_size: usize = mem::size_of::<I>();
-        unsafe {
-            let cur_len = (gap - start)/item_size;
-            let rem_len = (end - pos)/item_size;
-            ptr::copy(self.pos, self.gap_pos, rem_len);
-            self.self_ref.set_len(cur_len + rem_len);
+        #[cfg(all(feature = "strict", debug_assertions))]
+        {
+            let unvisited = self.raw.remaining();
+            if !self.stopped_explicitly && self.on_drop == OnDrop::KeepRest && unvisited > 0 {
+                panic!(
+                    "VecDrainWhere dropped with {} unvisited elements, \
+                     use `keep_rest`/`finish` if stopping early is intended",
+                    unvisited
+                );
+            }
         }
+        self.apply_drop_policy();
     }
 }
 
+/// Iterator draining all elements not yet visited, see `VecDrainWhere::drain_rest`.
+#[must_use]
+#[derive(Debug)]
+pub struct DrainRest<'a, Item: 'a> {
+    raw: RawDrain<'a, Item>
+}
+
+impl<'a, I: 'a> Iterator for DrainRest<'a, I> {
+    type Item = I;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.take_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.raw.remaining();
+        (len, Some(len))
+    }
+}
+
+impl<'a, I: 'a> DoubleEndedIterator for DrainRest<'a, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.take_back()
+    }
+}
+
+impl<'a, I: 'a> ExactSizeIterator for DrainRest<'a, I> {}
 
+impl<'a, I: 'a> FusedIterator for DrainRest<'a, I> {}
+
+impl<'a, I: 'a> Drop for DrainRest<'a, I> {
+    /// Drops all elements not yet consumed, except if
+    /// dropped because of a panic in which case they
+    /// are kept in the vector.
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.raw.drop_rest();
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
     use quickcheck::TestResult;
@@ -326,6 +966,638 @@ mod tests {
             let res = panic_situations(vec![(true, false)]);
             assert!(!res.is_error(), "{:?}", res);
         }
+
+        fn panic_with_policy(mask: Vec<(bool, bool)>, from_back: bool, policy: u8) -> TestResult {
+            use std::rc::Rc;
+            use OnPanic;
+
+            let policy = match policy % 3 {
+                0 => OnPanic::Leak,
+                1 => OnPanic::Keep,
+                _ => OnPanic::Drop
+            };
+            let counter = Rc::new(());
+            let mut data = (0..mask.len()).map(|idx| (idx, counter.clone())).collect::<Vec<_>>();
+            let mut visit_order = (0..mask.len()).collect::<Vec<_>>();
+            if from_back {
+                visit_order.reverse();
+            }
+            let panic_at = visit_order.iter().cloned().find(|idx| mask[*idx].1);
+            let expected = (0..mask.len()).filter(|idx| {
+                let visited = match panic_at {
+                    None => true,
+                    Some(pidx) if from_back => *idx > pidx,
+                    Some(pidx) => *idx < pidx
+                };
+                if Some(*idx) == panic_at {
+                    policy == OnPanic::Keep
+                } else {
+                    !visited || !mask[*idx].0
+                }
+            }).collect::<Vec<_>>();
+
+            let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                let iter = data.e_drain_where(|item| {
+                    let (mask, do_panic) = mask[item.0];
+                    if do_panic {
+                        panic!("-- yes panic --");
+                    }
+                    mask
+                }).with_panic_policy(policy);
+                if from_back {
+                    iter.rev().for_each(drop);
+                } else {
+                    iter.for_each(drop);
+                }
+            }));
+
+            if res.is_err() != panic_at.is_some() {
+                return TestResult::error(format!("unexpected panic result {:?}", mask));
+            }
+            let remaining = data.iter().map(|item| item.0).collect::<Vec<_>>();
+            if remaining != expected {
+                return TestResult::error(format!(
+                    "{:?}: remaining {:?}, exp {:?} ({:?})", policy, remaining, expected, mask));
+            }
+            drop(data);
+            let leaked = Rc::strong_count(&counter) - 1;
+            let expected_leaked = if panic_at.is_some() && policy == OnPanic::Leak { 1 } else { 0 };
+            if leaked != expected_leaked {
+                return TestResult::error(format!(
+                    "{:?}: leaked {}, exp {} ({:?})", policy, leaked, expected_leaked, mask));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_panic_with_policy() {
+            ::quickcheck::quickcheck(panic_with_policy as fn(Vec<(bool, bool)>, bool, u8) -> TestResult)
+        }
+    }
+
+    mod check_size_hint {
+        use super::*;
+
+        fn size_hint_bounds(mask: Vec<bool>) -> TestResult {
+            let mut data = (0..mask.len()).collect::<Vec<_>>();
+            let mut mask_iter = mask.clone().into_iter();
+            let mut iter = data.e_drain_where(|_| mask_iter.next().unwrap_or(false));
+            let mut remaining_matches = mask.iter().filter(|m| **m).count();
+            let mut unvisited = mask.len();
+            loop {
+                let (low, high) = iter.size_hint();
+                if low > remaining_matches || high.map(|h| h < remaining_matches) != Some(false) {
+                    return TestResult::error(format!(
+                        "bad size hint ({}, {:?}) for {} remaining matches",
+                        low, high, remaining_matches
+                    ));
+                }
+                if high != Some(unvisited) {
+                    return TestResult::error(format!(
+                        "upper bound {:?} but {} unvisited", high, unvisited));
+                }
+                match iter.next() {
+                    Some(_) => remaining_matches -= 1,
+                    None => break
+                }
+                unvisited = iter.raw.remaining();
+            }
+            if iter.size_hint() != (0, Some(0)) {
+                return TestResult::error("non empty size hint after completion");
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_size_hint_bounds() {
+            ::quickcheck::quickcheck(size_hint_bounds as fn(Vec<bool>) -> TestResult);
+        }
+
+        #[test]
+        fn upper_bound_is_not_vec_len() {
+            let mut data = vec![1, 2, 3, 4];
+            let iter = data.e_drain_where(|_| true);
+            assert_eq!(iter.size_hint(), (0, Some(4)));
+            assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        }
+
+        #[test]
+        fn is_fused() {
+            let mut data = vec![1, 2, 3];
+            let mut iter = data.e_drain_where(|x| *x == 2);
+            assert_eq!(iter.next(), Some(2));
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.size_hint(), (0, Some(0)));
+            drop(iter);
+            assert_eq!(data, vec![1, 3]);
+        }
+    }
+
+    mod check_zero_sized {
+        use super::*;
+        use std::cell::Cell;
+
+        #[test]
+        // a `()` can't be uninitialized and zero sized types never
+        // need to allocate (`vec![(); usize::MAX]` would loop instead)
+        #[allow(clippy::uninit_vec)]
+        fn more_than_isize_max_units() {
+            let mut data: Vec<()> = Vec::new();
+            unsafe { data.set_len(usize::MAX) }
+            {
+                let mut iter = data.e_drain_where(|_| true);
+                assert_eq!(iter.next(), Some(()));
+                iter.keep_rest();
+            }
+            assert_eq!(data.len(), usize::MAX - 1);
+        }
+
+        fn unit_with_mask(mask: Vec<bool>, stop_after: usize) -> TestResult {
+            let mut data = vec![(); mask.len()];
+            let mut mask_iter = mask.clone().into_iter();
+            let mut calls = 0;
+            let drained = {
+                let mut iter = data.e_drain_where(|_| {
+                    calls += 1;
+                    mask_iter.next().unwrap_or(false)
+                });
+                let drained = iter.by_ref().take(stop_after).count();
+                iter.keep_rest();
+                drained
+            };
+
+            let expected_drained = mask.iter().filter(|m| **m).count().min(stop_after);
+            if drained != expected_drained {
+                return TestResult::error(format!(
+                    "drained {}, expected {} ({:?})", drained, expected_drained, mask));
+            }
+            if calls > mask.len() {
+                return TestResult::error("called predicate to often");
+            }
+            if data.len() != mask.len() - drained {
+                return TestResult::error(format!(
+                    "resulting len {}, expected {} ({:?})",
+                    data.len(), mask.len() - drained, mask));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_unit_with_mask() {
+            ::quickcheck::quickcheck(unit_with_mask as fn(Vec<bool>, usize) -> TestResult);
+        }
+
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug)]
+        struct Marker;
+
+        impl Drop for Marker {
+            fn drop(&mut self) {
+                DROPS.with(|d| d.set(d.get() + 1));
+            }
+        }
+
+        #[test]
+        fn zero_sized_elements_are_dropped_once() {
+            DROPS.with(|d| d.set(0));
+            let mut data = (0..10).map(|_| Marker).collect::<Vec<_>>();
+            let mut idx = 0;
+            let drained = {
+                let mut iter = data.e_drain_where(|_| {
+                    idx += 1;
+                    idx % 2 == 0
+                });
+                let drained = iter.by_ref().take(3).count();
+                iter.keep_rest();
+                drained
+            };
+            assert_eq!(drained, 3);
+            assert_eq!(DROPS.with(|d| d.get()), 3);
+            assert_eq!(data.len(), 7);
+            drop(data);
+            assert_eq!(DROPS.with(|d| d.get()), 10);
+        }
+    }
+
+    mod check_double_ended {
+        use super::*;
+
+        /// For each element a `(drain, from_back)` pair, `from_back`
+        /// decides if `next_back` or `next` is used for the next step
+        /// and `stop_after` is the number of steps before dropping.
+        fn mixed_directions(mask: Vec<bool>, dirs: Vec<bool>, stop_after: usize) -> TestResult {
+            let mut data = (0..mask.len()).collect::<Vec<_>>();
+            let mut drained = Vec::new();
+            {
+                let mut iter = data.e_drain_where(|el| mask[*el]);
+                let mut dirs = dirs.iter().cloned().chain(::std::iter::repeat(false));
+                for _ in 0..stop_after {
+                    let next = if dirs.next().unwrap() { iter.next_back() } else { iter.next() };
+                    match next {
+                        Some(el) => drained.push(el),
+                        None => break
+                    }
+                }
+                iter.keep_rest();
+            }
+
+            let mut all = data.iter().chain(drained.iter()).cloned().collect::<Vec<_>>();
+            all.sort();
+            if all != (0..mask.len()).collect::<Vec<_>>() {
+                return TestResult::error(format!("lost/duplicated elements {:?} {:?}", data, drained));
+            }
+            if drained.iter().any(|el| !mask[*el]) {
+                return TestResult::error(format!("drained unmasked element {:?}", drained));
+            }
+            if data.windows(2).any(|w| w[0] >= w[1]) {
+                return TestResult::error(format!("order not kept {:?}", data));
+            }
+            if stop_after > mask.len() && data.iter().any(|el| mask[*el]) {
+                return TestResult::error(format!("not all drained {:?}", data));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_mixed_directions() {
+            ::quickcheck::quickcheck(mixed_directions as fn(Vec<bool>, Vec<bool>, usize) -> TestResult);
+        }
+
+        #[test]
+        fn rev_drains_newest_first() {
+            let mut data = vec![1, 2, 3, 4, 5, 6, 7];
+            let mut iter = data.e_drain_where(|x| *x % 2 == 1);
+            let newest = iter.by_ref().rev().take(2).collect::<Vec<_>>();
+            iter.keep_rest();
+            assert_eq!(newest, vec![7, 5]);
+            assert_eq!(data, vec![1, 2, 3, 4, 6]);
+        }
+
+        #[test]
+        fn unit_from_both_ends() {
+            let mut data = vec![(); 5];
+            let mut count = 0;
+            {
+                let mut iter = data.e_drain_where(|_| { count += 1; count != 3 });
+                assert_eq!(iter.next_back(), Some(()));
+                assert_eq!(iter.next(), Some(()));
+                assert_eq!(iter.next_back(), Some(()));
+                iter.keep_rest();
+            }
+            assert_eq!(data.len(), 2);
+        }
+    }
+
+    mod check_range {
+        use super::*;
+
+        fn range_with_mask(mask: Vec<bool>, a: usize, b: usize) -> TestResult {
+            let len = mask.len();
+            let (start, end) = if len == 0 { (0, 0) } else {
+                let (a, b) = (a % (len + 1), b % (len + 1));
+                (a.min(b), a.max(b))
+            };
+            let mut data = (0..len).collect::<Vec<_>>();
+            let mut visited = Vec::new();
+            let drained = data.e_drain_where_range(start..end, |el| {
+                visited.push(*el);
+                mask[*el]
+            }).collect::<Vec<_>>();
+
+            if visited != (start..end).collect::<Vec<_>>() {
+                return TestResult::error(format!("visited {:?} for range {}..{}", visited, start, end));
+            }
+            let expected_drained = (start..end).filter(|el| mask[*el]).collect::<Vec<_>>();
+            if drained != expected_drained {
+                return TestResult::error(format!("drained {:?}, exp {:?}", drained, expected_drained));
+            }
+            let expected = (0..len).filter(|el| *el < start || *el >= end || !mask[*el])
+                .collect::<Vec<_>>();
+            if data != expected {
+                return TestResult::error(format!("remaining {:?}, exp {:?}", data, expected));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_range_with_mask() {
+            ::quickcheck::quickcheck(range_with_mask as fn(Vec<bool>, usize, usize) -> TestResult);
+        }
+
+        #[test]
+        fn early_stop_in_range() {
+            let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+            {
+                let mut iter = data.e_drain_where_range(2..6, |_| true);
+                assert_eq!(iter.next(), Some(3));
+                iter.keep_rest();
+            }
+            assert_eq!(data, vec![1, 2, 4, 5, 6, 7, 8]);
+            {
+                let mut iter = data.e_drain_where_range(2..=5, |_| true);
+                assert_eq!(iter.next_back(), Some(7));
+                iter.keep_rest();
+            }
+            assert_eq!(data, vec![1, 2, 4, 5, 6, 8]);
+        }
+
+        #[test]
+        #[should_panic]
+        fn range_out_of_bounds() {
+            let mut data = vec![1, 2, 3];
+            let _ = data.e_drain_where_range(1..4, |_| true);
+        }
+
+        #[test]
+        #[should_panic]
+        fn range_start_after_end() {
+            let mut data = vec![1, 2, 3];
+            #[allow(clippy::reversed_empty_ranges)]
+            let _ = data.e_drain_where_range(2..1, |_| true);
+        }
+    }
+
+    mod check_drop_policy {
+        use super::*;
+        use OnDrop;
+
+        fn policy_with_mask(mask: Vec<bool>, stop_after: usize, policy: u8) -> TestResult {
+            let policy = match policy % 3 {
+                0 => OnDrop::KeepRest,
+                1 => OnDrop::DropMatching,
+                _ => OnDrop::DrainAll
+            };
+            let mut data = (0..mask.len()).collect::<Vec<_>>();
+            let drained = {
+                let mut iter = data.e_drain_where(|el| mask[*el])
+                    .with_drop_policy(policy);
+                let drained = iter.by_ref().take(stop_after).collect::<Vec<_>>();
+                if policy == OnDrop::KeepRest {
+                    iter.keep_rest();
+                }
+                drained
+            };
+
+            let last_visited = drained.last().map(|el| *el + 1).unwrap_or(0);
+            let stopped_early = drained.len() == stop_after;
+            let expected = (0..mask.len()).filter(|el| {
+                if !stopped_early || *el < last_visited {
+                    !mask[*el]
+                } else {
+                    match policy {
+                        OnDrop::KeepRest => true,
+                        OnDrop::DropMatching => !mask[*el],
+                        OnDrop::DrainAll => false
+                    }
+                }
+            }).collect::<Vec<_>>();
+
+            if data != expected {
+                return TestResult::error(format!(
+                    "{:?}: remaining {:?}, exp {:?}", policy, data, expected));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_policy_with_mask() {
+            ::quickcheck::quickcheck(policy_with_mask as fn(Vec<bool>, usize, u8) -> TestResult);
+        }
+
+        #[test]
+        fn drop_matching_keeps_other_elements() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let first = data.e_drain_where(|x| *x % 2 == 0)
+                .with_drop_policy(OnDrop::DropMatching)
+                .next();
+            assert_eq!(first, Some(2));
+            assert_eq!(data, vec![1, 3, 5]);
+        }
+
+        #[test]
+        fn keep_rest_on_panic() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                let mut iter = data.e_drain_where(|x| *x % 2 == 0)
+                    .with_drop_policy(OnDrop::DrainAll);
+                iter.next();
+                panic!("-- yes panic --");
+            }));
+            assert!(res.is_err());
+            assert_eq!(data, vec![1, 3, 4, 5, 6]);
+        }
+    }
+
+    mod check_explicit_stop {
+        use super::*;
+        use {OnDrop, DrainReport};
+
+        #[test]
+        fn keep_rest_ignores_drop_policy() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let report = {
+                let mut iter = data.e_drain_where(|x| *x % 2 == 0)
+                    .with_drop_policy(OnDrop::DrainAll);
+                assert_eq!(iter.next(), Some(2));
+                iter.keep_rest()
+            };
+            assert_eq!(report, DrainReport { drained: 1, kept: 1, unvisited: 4 });
+            assert_eq!(data, vec![1, 3, 4, 5, 6]);
+        }
+
+        #[test]
+        fn finish_applies_drop_policy() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let report = {
+                let mut iter = data.e_drain_where(|x| *x % 2 == 0)
+                    .with_drop_policy(OnDrop::DropMatching);
+                assert_eq!(iter.next_back(), Some(6));
+                iter.finish()
+            };
+            assert_eq!(report, DrainReport { drained: 3, kept: 3, unvisited: 0 });
+            assert_eq!(data, vec![1, 3, 5]);
+        }
+
+        #[test]
+        fn finish_with_range() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let report = {
+                let mut iter = data.e_drain_where_range(1..5, |x| *x % 2 == 0);
+                assert_eq!(iter.next(), Some(2));
+                assert_eq!(iter.next_back(), Some(4));
+                iter.finish()
+            };
+            assert_eq!(report, DrainReport { drained: 2, kept: 1, unvisited: 1 });
+            assert_eq!(data, vec![1, 3, 5, 6]);
+        }
+
+        #[test]
+        fn drain_rest_drains_unvisited() {
+            let mut data = vec![1, 2, 3, 4, 5, 6, 7];
+            {
+                let mut iter = data.e_drain_where(|x| *x % 2 == 0);
+                assert_eq!(iter.next(), Some(2));
+                assert_eq!(iter.next_back(), Some(6));
+                let mut rest = iter.drain_rest();
+                assert_eq!(rest.len(), 3);
+                assert_eq!(rest.next(), Some(3));
+                assert_eq!(rest.next_back(), Some(5));
+            }
+            assert_eq!(data, vec![1, 7]);
+        }
+
+        fn report_counts(mask: Vec<bool>, stop_after: usize) -> TestResult {
+            let mut data = (0..mask.len()).collect::<Vec<_>>();
+            let mut iter = data.e_drain_where(|el| mask[*el]);
+            let drained = iter.by_ref().take(stop_after).count();
+            let report = iter.keep_rest();
+            if report.drained != drained {
+                return TestResult::error(format!("drained {}, reported {:?}", drained, report));
+            }
+            if report.drained + report.kept + report.unvisited != mask.len() {
+                return TestResult::error(format!("counts don't add up {:?} {:?}", report, mask));
+            }
+            if data.len() != report.kept + report.unvisited {
+                return TestResult::error(format!("len {}, reported {:?}", data.len(), report));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_report_counts() {
+            ::quickcheck::quickcheck(report_counts as fn(Vec<bool>, usize) -> TestResult);
+        }
+    }
+
+    #[cfg(all(feature = "strict", debug_assertions))]
+    mod check_strict {
+        use super::*;
+        use OnDrop;
+
+        #[test]
+        #[should_panic(expected = "unvisited elements")]
+        fn implicit_early_stop_panics() {
+            let mut data = vec![1, 2, 3, 4];
+            let _ = data.e_drain_where(|x| *x % 2 == 0).any(|x| x == 2);
+        }
+
+        #[test]
+        fn implicit_early_stop_keeps_elements() {
+            let mut data = vec![1, 2, 3, 4];
+            let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                data.e_drain_where(|x| *x % 2 == 0).next();
+            }));
+            assert!(res.is_err());
+            assert_eq!(data, vec![1, 3, 4]);
+        }
+
+        #[test]
+        fn explicit_or_complete_stops_are_fine() {
+            let mut data = vec![1, 2, 3, 4, 5, 6];
+            let mut iter = data.e_drain_where(|x| *x % 2 == 0);
+            assert_eq!(iter.next(), Some(2));
+            iter.keep_rest();
+            let mut iter = data.e_drain_where(|x| *x % 2 == 0);
+            assert_eq!(iter.next(), Some(4));
+            iter.finish();
+            data.e_drain_where(|x| *x % 2 == 0)
+                .with_drop_policy(OnDrop::DrainAll)
+                .next();
+            assert_eq!(data, vec![1, 3, 5]);
+            data.e_drain_where(|_| false).for_each(drop);
+            assert_eq!(data, vec![1, 3, 5]);
+        }
+    }
+
+    mod check_peek {
+        use super::*;
+        use OnDrop;
+
+        #[test]
+        fn peek_then_stop_keeps_element() {
+            let mut data = vec![1, 2, 3, 4, 5];
+            let mut calls = 0;
+            {
+                let mut iter = data.e_drain_where(|x| { calls += 1; *x % 2 == 0 });
+                assert_eq!(iter.next(), Some(2));
+                {
+                    let peeked = iter.peek_mut().unwrap();
+                    assert_eq!(*peeked, 4);
+                    *peeked = 40;
+                }
+                assert_eq!(iter.peek_mut().map(|x| *x), Some(40));
+                assert_eq!(iter.size_hint(), (1, Some(2)));
+                iter.keep_rest();
+            }
+            assert_eq!(calls, 4);
+            assert_eq!(data, vec![1, 3, 40, 5]);
+        }
+
+        #[test]
+        fn peek_then_next_does_not_call_predicate_again() {
+            let mut data = vec![1, 2, 3, 4, 5];
+            let mut calls = 0;
+            {
+                let mut iter = data.e_drain_where(|x| { calls += 1; *x % 2 == 0 });
+                assert_eq!(iter.peek_mut().map(|x| *x), Some(2));
+                assert_eq!(iter.next(), Some(2));
+                assert_eq!(iter.peek_mut().map(|x| *x), Some(4));
+                assert_eq!(iter.next_back(), Some(4));
+                assert_eq!(iter.peek_mut(), None);
+                assert_eq!(iter.next(), None);
+            }
+            assert_eq!(calls, 5);
+            assert_eq!(data, vec![1, 3, 5]);
+        }
+
+        #[test]
+        fn peeked_element_is_handled_by_drop_policy() {
+            let mut data = vec![1, 2, 3, 4];
+            {
+                let mut iter = data.e_drain_where(|x| *x % 2 == 0)
+                    .with_drop_policy(OnDrop::DropMatching);
+                assert_eq!(iter.peek_mut().map(|x| *x), Some(2));
+            }
+            assert_eq!(data, vec![1, 3]);
+        }
+
+        fn peek_with_mask(mask: Vec<bool>, peeks: Vec<bool>) -> TestResult {
+            let mut data = (0..mask.len()).collect::<Vec<_>>();
+            let mut drained = Vec::new();
+            let mut calls = 0;
+            {
+                let mut iter = data.e_drain_where(|el| { calls += 1; mask[*el] });
+                let mut peeks = peeks.into_iter().chain(::std::iter::repeat(false));
+                loop {
+                    let peeked = if peeks.next().unwrap() { iter.peek_mut().map(|x| *x) } else { None };
+                    match iter.next() {
+                        Some(el) => {
+                            if peeked.is_some() && peeked != Some(el) {
+                                return TestResult::error(format!("peeked {:?}, got {}", peeked, el));
+                            }
+                            drained.push(el)
+                        },
+                        None => break
+                    }
+                }
+            }
+            if calls != mask.len() {
+                return TestResult::error(format!("called predicate {} times", calls));
+            }
+            if drained != (0..mask.len()).filter(|el| mask[*el]).collect::<Vec<_>>() {
+                return TestResult::error(format!("drained {:?} ({:?})", drained, mask));
+            }
+            TestResult::passed()
+        }
+
+        #[test]
+        fn qc_peek_with_mask() {
+            ::quickcheck::quickcheck(peek_with_mask as fn(Vec<bool>, Vec<bool>) -> TestResult);
+        }
     }
 
 }
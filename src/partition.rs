@@ -0,0 +1,105 @@
+//! Routing elements into multiple buckets in a single pass.
+use raw::RawDrain;
+use OnPanic;
+
+/// See `VecDrainWhereExt::e_partition_into`.
+pub(crate) fn partition_into<I, C>(vec: &mut Vec<I>, mut classifier: C, buckets: &mut [Vec<I>]) -> usize
+    where C: FnMut(&mut I) -> Option<usize>
+{
+    let mut raw = RawDrain::new(vec, ..);
+    let nr_buckets = buckets.len();
+    let mut moved = 0;
+    let mut target = 0;
+    while raw.seek_front(OnPanic::Leak, |_, item| {
+        match classifier(item) {
+            Some(bucket) => {
+                assert!(bucket < nr_buckets,
+                    "bucket index {} out of range for {} buckets", bucket, nr_buckets);
+                target = bucket;
+                true
+            },
+            None => false
+        }
+    }) {
+        let item = raw.take_front().expect("found element to move");
+        buckets[target].push(item);
+        moved += 1;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn partition_by_mod(data: Vec<u8>, nr_buckets: u8) -> TestResult {
+        let nr_buckets = (nr_buckets % 5) as usize;
+        let classify = |el: &u8| {
+            let class = (*el as usize) % (nr_buckets + 1);
+            if class == nr_buckets { None } else { Some(class) }
+        };
+        let mut expected = vec![Vec::new(); nr_buckets];
+        let mut expected_rest = Vec::new();
+        for el in data.iter() {
+            match classify(el) {
+                Some(bucket) => expected[bucket].push(*el),
+                None => expected_rest.push(*el)
+            }
+        }
+
+        let mut data = data;
+        let mut buckets = vec![Vec::new(); nr_buckets];
+        let moved = data.e_partition_into(|el| classify(el), &mut buckets);
+        if buckets != expected || data != expected_rest
+            || moved != expected.iter().map(|b| b.len()).sum::<usize>()
+        {
+            return TestResult::error(format!("{:?}/{:?} != {:?}/{:?}", buckets, data, expected, expected_rest));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_partition_by_mod() {
+        ::quickcheck::quickcheck(partition_by_mod as fn(Vec<u8>, u8) -> TestResult);
+    }
+
+    #[test]
+    fn appends_to_existing_buckets() {
+        let mut data = vec![1, 2, 3, 4, 5, 6];
+        let mut buckets = vec![vec![0], vec![]];
+        data.e_partition_into(|x| if *x > 4 { None } else { Some(*x % 2) }, &mut buckets);
+        assert_eq!(buckets, vec![vec![0, 2, 4], vec![1, 3]]);
+        assert_eq!(data, vec![5, 6]);
+    }
+
+    #[test]
+    fn panic_keeps_unvisited() {
+        let counter = Rc::new(());
+        let mut data = (0..6).map(|el| (el, counter.clone())).collect::<Vec<_>>();
+        let mut buckets = vec![Vec::new(), Vec::new()];
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            data.e_partition_into(|el| {
+                if el.0 == 3 { panic!("3") }
+                if el.0 == 1 { None } else { Some(el.0 % 2) }
+            }, &mut buckets)
+        }));
+        assert!(res.is_err());
+        assert_eq!(buckets[0].iter().map(|el| el.0).collect::<Vec<_>>(), vec![0, 2]);
+        assert!(buckets[1].is_empty());
+        // the element the classifier panicked on is leaked
+        assert_eq!(data.iter().map(|el| el.0).collect::<Vec<_>>(), vec![1, 4, 5]);
+        drop(data);
+        drop(buckets);
+        assert_eq!(Rc::strong_count(&counter), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "bucket index 2 out of range for 2 buckets")]
+    fn out_of_range_bucket_panics() {
+        let mut data = vec![1, 2, 3];
+        data.e_partition_into(|x| Some(*x - 1), &mut [Vec::new(), Vec::new()]);
+    }
+}
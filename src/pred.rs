@@ -0,0 +1,132 @@
+//! Composable predicates for `e_drain_where`.
+//!
+//! All functions return a `FnMut(&mut T) -> bool` closure, so they
+//! can be passed to `e_drain_where` (and its variants) directly or
+//! be combined with each other, e.g.:
+//!
+//! ```
+//! use vec_drain_where::VecDrainWhereExt;
+//! use vec_drain_where::pred::{and, not, by_key, between, eq};
+//!
+//! let mut data = vec![(1, 'a'), (5, 'b'), (7, 'a'), (9, 'c')];
+//! let drained = data.e_drain_where(and(
+//!     by_key(|x: &mut (u32, char)| x.0, between(3, 8)),
+//!     not(by_key(|x: &mut (u32, char)| x.1, eq('b')))
+//! )).collect::<Vec<_>>();
+//! assert_eq!(drained, vec![(7, 'a')]);
+//! ```
+use std::hash::{Hash, BuildHasher};
+use std::collections::HashSet;
+
+/// True if both predicates are true (short circuiting).
+pub fn and<T, A, B>(mut a: A, mut b: B) -> impl FnMut(&mut T) -> bool
+    where A: FnMut(&mut T) -> bool, B: FnMut(&mut T) -> bool
+{
+    move |item| a(item) && b(item)
+}
+
+/// True if any of the predicates is true (short circuiting).
+pub fn or<T, A, B>(mut a: A, mut b: B) -> impl FnMut(&mut T) -> bool
+    where A: FnMut(&mut T) -> bool, B: FnMut(&mut T) -> bool
+{
+    move |item| a(item) || b(item)
+}
+
+/// True if the predicate is false.
+pub fn not<T, P>(mut pred: P) -> impl FnMut(&mut T) -> bool
+    where P: FnMut(&mut T) -> bool
+{
+    move |item| !pred(item)
+}
+
+/// Applies the predicate to the key extracted from the element.
+pub fn by_key<T, K, F, P>(mut key: F, mut pred: P) -> impl FnMut(&mut T) -> bool
+    where F: FnMut(&mut T) -> K, P: FnMut(&mut K) -> bool
+{
+    move |item| pred(&mut key(item))
+}
+
+/// True if the element is equal to given value.
+pub fn eq<T>(value: T) -> impl FnMut(&mut T) -> bool
+    where T: PartialEq
+{
+    move |item| *item == value
+}
+
+/// True if the element is contained in given set.
+pub fn in_set<'a, T, S>(set: &'a HashSet<T, S>) -> impl FnMut(&mut T) -> bool + 'a
+    where T: Eq + Hash + 'a, S: BuildHasher
+{
+    move |item| set.contains(item)
+}
+
+/// True if the element is in the (inclusive) range `lo..=hi`.
+pub fn between<T>(lo: T, hi: T) -> impl FnMut(&mut T) -> bool
+    where T: PartialOrd
+{
+    move |item| lo <= *item && *item <= hi
+}
+
+/// True for the first `n` calls, false afterwards.
+///
+/// Combined with `and` this drains only the first `n` matches of
+/// a predicate, e.g. `and(pred, first_n(n))`, as `and` only calls
+/// `first_n` if `pred` is true. Note that the predicate is still
+/// called for all elements, to stop visiting elements once `n`
+/// elements are drained use `e_drain_where_max`.
+pub fn first_n<T>(mut n: usize) -> impl FnMut(&mut T) -> bool {
+    move |_| {
+        if n > 0 {
+            n -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+    use super::*;
+
+    #[test]
+    fn first_n_matches() {
+        let mut data = (0..10).collect::<Vec<u32>>();
+        let drained = data.e_drain_where(and(|x: &mut u32| *x % 2 == 1, first_n(3)))
+            .collect::<Vec<_>>();
+        assert_eq!(drained, vec![1, 3, 5]);
+        assert_eq!(data, vec![0, 2, 4, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn in_set_or_eq() {
+        let set = [2, 4, 6].iter().cloned().collect::<HashSet<u32>>();
+        let mut data = (0..8).collect::<Vec<u32>>();
+        let drained = data.e_drain_where(or(in_set(&set), eq(7))).collect::<Vec<_>>();
+        assert_eq!(drained, vec![2, 4, 6, 7]);
+        assert_eq!(data, vec![0, 1, 3, 5]);
+    }
+
+    fn combinators_like_closures(data: Vec<(u8, bool)>, lo: u8, hi: u8) -> TestResult {
+        let mut data1 = data.clone();
+        let mut data2 = data.clone();
+        let drained1 = data1.e_drain_where(or(
+            by_key(|x: &mut (u8, bool)| x.0, between(lo, hi)),
+            not(by_key(|x: &mut (u8, bool)| x.1, eq(true)))
+        )).collect::<Vec<_>>();
+        let drained2 = data2.e_drain_where(|x| (lo <= x.0 && x.0 <= hi) || !x.1)
+            .collect::<Vec<_>>();
+        if drained1 != drained2 || data1 != data2 {
+            return TestResult::error(format!("{:?}/{:?} != {:?}/{:?}", drained1, data1, drained2, data2));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_combinators_like_closures() {
+        ::quickcheck::quickcheck(combinators_like_closures as fn(Vec<(u8, bool)>, u8, u8) -> TestResult);
+    }
+}
@@ -0,0 +1,491 @@
+//! Gap buffer machinery shared by the drain iterators.
+use std::{ptr, mem};
+use std::mem::ManuallyDrop;
+use std::ops::{Bound, RangeBounds};
+
+use {OnPanic, DrainReport, Decision};
+
+/// State of a vector which is (conditionally) drained in place.
+///
+/// Positions are tracked as element indices instead of pointers,
+/// as pointer offsets don't advance for zero sized types.
+///
+/// While it lives the (original) vector is split into following
+/// sections:
+///
+/// - `[0, gap_pos)`: elements kept when iterating from the front
+/// - `[gap_pos, pos)`: gap left by drained elements
+/// - `[pos, end)`: elements not yet visited
+/// - `[end, back_gap_pos)`: gap left by elements drained from the back
+/// - `[back_gap_pos, len)`: elements kept when iterating from the back
+///
+/// Where `[start, range_end)` is the range of elements which is drained,
+/// i.e. `[0, start)` and `[range_end, len)` are always kept.
+///
+/// For safety reasons the length of the vector is set to 0 while
+/// it lives, on drop the sections are stitched back together.
+#[derive(Debug)]
+pub(crate) struct RawDrain<'a, Item: 'a> {
+    start: usize,
+    range_end: usize,
+    pos: usize,
+    gap_pos: usize,
+    end: usize,
+    back_gap_pos: usize,
+    len: usize,
+    stopped: bool,
+    vec: &'a mut Vec<Item>
+}
+
+impl<'a, Item: 'a> RawDrain<'a, Item> {
+
+    /// Starts draining given range of the vector.
+    ///
+    /// Elements before the range are treated as already kept,
+    /// elements after it as kept from the back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end
+    /// or if the end of the range is greater than the length of
+    /// the vector.
+    pub(crate) fn new<R>(vec: &'a mut Vec<Item>, range: R) -> Self
+        where R: RangeBounds<usize>
+    {
+        let len = vec.len();
+
+        let start = match range.start_bound() {
+            Bound::Included(&idx) => idx,
+            Bound::Excluded(&idx) => idx.checked_add(1)
+                .expect("range start overflows usize"),
+            Bound::Unbounded => 0
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&idx) => idx.checked_add(1)
+                .expect("range end overflows usize"),
+            Bound::Excluded(&idx) => idx,
+            Bound::Unbounded => len
+        };
+        if start > end {
+            panic!("range start {} is greater then range end {}", start, end);
+        }
+        if end > len {
+            panic!("range end {} is out of bounds for length {}", end, len);
+        }
+
+        // leak amplification for safety
+        unsafe { vec.set_len(0) }
+
+        RawDrain {
+            start,
+            range_end: end,
+            pos: start,
+            gap_pos: start,
+            end,
+            back_gap_pos: end,
+            len,
+            stopped: false,
+            vec
+        }
+    }
+
+    /// Returns a pointer to the slot at given index.
+    ///
+    /// For zero sized types this is always the same (dangling but
+    /// well aligned) pointer, which is fine as reading/writing
+    /// zero sized types through it is a no-op.
+    pub(crate) fn slot(&mut self, idx: usize) -> *mut Item {
+        debug_assert!(idx <= self.len);
+        unsafe { self.vec.as_mut_ptr().add(idx) }
+    }
+
+    /// Number of elements not yet visited.
+    pub(crate) fn remaining(&self) -> usize {
+        self.end - self.pos
+    }
+
+    /// Upper bound of the number of elements which still can be drained
+    /// by `next_front`/`next_back`, i.e. 0 if a predicate decided to stop.
+    pub(crate) fn upper_bound(&self) -> usize {
+        if self.stopped { 0 } else { self.remaining() }
+    }
+
+    /// Stops draining, no further elements are visited.
+    pub(crate) fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Counts of drained, kept and not yet visited elements.
+    pub(crate) fn report(&self) -> DrainReport {
+        DrainReport {
+            drained: (self.pos - self.gap_pos) + (self.back_gap_pos - self.end),
+            kept: (self.gap_pos - self.start) + (self.range_end - self.back_gap_pos),
+            unvisited: self.remaining()
+        }
+    }
+
+    /// Index of the next not yet visited element from the front.
+    pub(crate) fn front_idx(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the next not yet visited element from the front.
+    pub(crate) fn front_mut(&mut self) -> Option<&mut Item> {
+        if self.pos < self.end {
+            let current = self.slot(self.pos);
+            Some(unsafe { &mut *current })
+        } else {
+            None
+        }
+    }
+
+    /// Drains the next not yet visited element from the front.
+    pub(crate) fn take_front(&mut self) -> Option<Item> {
+        if self.pos < self.end {
+            let current = self.slot(self.pos);
+            self.pos += 1;
+            Some(unsafe { ptr::read(current) })
+        } else {
+            None
+        }
+    }
+
+    /// Drains the next not yet visited element from the back.
+    pub(crate) fn take_back(&mut self) -> Option<Item> {
+        if self.pos < self.end {
+            self.end -= 1;
+            let current = self.slot(self.end);
+            Some(unsafe { ptr::read(current) })
+        } else {
+            None
+        }
+    }
+
+    /// Keeps the next `n` (or less if there are less) not yet visited
+    /// elements, moving them to the front gap. Returns the number of
+    /// elements kept.
+    pub(crate) fn keep_front(&mut self, n: usize) -> usize {
+        let n = n.min(self.remaining());
+        if self.gap_pos < self.pos {
+            let src = self.slot(self.pos);
+            let dest = self.slot(self.gap_pos);
+            unsafe { ptr::copy(src, dest, n) }
+        }
+        self.gap_pos += n;
+        self.pos += n;
+        n
+    }
+
+    /// Index of the end of the front gap, i.e. where the next
+    /// element written with `put_front` ends up.
+    pub(crate) fn front_gap_idx(&self) -> usize {
+        self.gap_pos
+    }
+
+    /// Number of slots in the front gap, i.e. how many elements
+    /// can be written with `put_front`.
+    pub(crate) fn front_gap_len(&self) -> usize {
+        self.pos - self.gap_pos
+    }
+
+    /// Writes given element to the front gap, i.e. keeps it.
+    ///
+    /// There must be space in the gap, e.g. because an element
+    /// was taken with `take_front` before.
+    pub(crate) fn put_front(&mut self, item: Item) {
+        assert!(self.gap_pos < self.pos, "no space in front gap");
+        let gap = self.slot(self.gap_pos);
+        unsafe { ptr::write(gap, item) }
+        self.gap_pos += 1;
+    }
+
+    /// Drains the last element kept from the front.
+    ///
+    /// There must be a kept element in the drained range.
+    pub(crate) fn take_last_kept(&mut self) -> Item {
+        assert!(self.start < self.gap_pos, "no element kept");
+        self.gap_pos -= 1;
+        let last = self.slot(self.gap_pos);
+        unsafe { ptr::read(last) }
+    }
+
+    /// Takes all kept elements starting at index `from` out of the vector.
+    ///
+    /// `from` must be a front gap index (see `front_gap_idx`) from
+    /// this drain.
+    pub(crate) fn take_kept_since(&mut self, from: usize) -> Vec<Item> {
+        assert!(self.start <= from && from <= self.gap_pos, "not a kept element");
+        let len = self.gap_pos - from;
+        let mut taken = Vec::with_capacity(len);
+        let src = self.slot(from);
+        unsafe {
+            ptr::copy_nonoverlapping(src, taken.as_mut_ptr(), len);
+            taken.set_len(len);
+        }
+        self.gap_pos = from;
+        taken
+    }
+
+    /// Writes given element to the back gap, i.e. keeps it.
+    ///
+    /// There must be space in the gap, e.g. because an element
+    /// was taken with `take_back` before.
+    pub(crate) fn put_back(&mut self, item: Item) {
+        assert!(self.end < self.back_gap_pos, "no space in back gap");
+        self.back_gap_pos -= 1;
+        let gap = self.slot(self.back_gap_pos);
+        unsafe { ptr::write(gap, item) }
+    }
+
+    /// Calls the predicate on the element at given index, if it
+    /// panics the element is handled as specified by `on_panic`.
+    fn decide<F, D>(&mut self, idx: usize, from_back: bool, on_panic: OnPanic, predicate: &mut F)
+        -> Decision
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>
+    {
+        let current = self.slot(idx);
+        let guard = PanicGuard { raw: self, idx, from_back, on_panic };
+        let decision = predicate(idx, unsafe { &mut *current }).into();
+        mem::forget(guard);
+        decision
+    }
+
+    /// Visits the elements from the front until the predicate
+    /// decides to drain one, returns true if it found one.
+    ///
+    /// The element to drain is left in place as the first not yet
+    /// visited element, i.e. `front_mut`/`take_front` return it.
+    ///
+    /// Elements the predicate decides to keep are moved to the
+    /// front gap. Once the predicate decided to stop no further
+    /// elements are visited.
+    pub(crate) fn seek_front<F, D>(&mut self, on_panic: OnPanic, mut predicate: F) -> bool
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>
+    {
+        while let Some(decision) = self.decide_front(on_panic, &mut predicate) {
+            if decision.is_drain() {
+                return true;
+            }
+            self.keep_front(1);
+        }
+        false
+    }
+
+    /// Calls the predicate on the next not yet visited element from
+    /// the front and returns its decision, without acting on it.
+    ///
+    /// Returns `None` if all elements where visited or the predicate
+    /// decided to stop before.
+    pub(crate) fn decide_front<F, D>(&mut self, on_panic: OnPanic, mut predicate: F) -> Option<Decision>
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>
+    {
+        if self.stopped || self.pos >= self.end {
+            return None;
+        }
+        let idx = self.pos;
+        let decision = self.decide(idx, false, on_panic, &mut predicate);
+        self.stopped = decision.is_stop();
+        Some(decision)
+    }
+
+    /// Visits the elements from the front until the predicate
+    /// decides to drain one and returns it together with its
+    /// index in the original vector.
+    ///
+    /// See `seek_front`.
+    pub(crate) fn next_front<F, D>(&mut self, on_panic: OnPanic, predicate: F)
+        -> Option<(usize, Item)>
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>
+    {
+        if self.seek_front(on_panic, predicate) {
+            let idx = self.pos;
+            self.take_front().map(|item| (idx, item))
+        } else {
+            None
+        }
+    }
+
+    /// Drains the next run of consecutive elements the predicate decides
+    /// to drain from the front and hands it to `sink`.
+    ///
+    /// The elements are passed while they are still in the buffer of
+    /// the vector, which is why the element ending the run is only
+    /// moved to the front gap (i.e. kept) after `sink` took the run.
+    /// If the predicate panics the part of the run collected so far
+    /// is passed to `RunSink::abort_run` instead.
+    ///
+    /// Returns the length of the run or `None` if no element was drained.
+    pub(crate) fn next_run<F, D, S>(&mut self, on_panic: OnPanic, mut predicate: F, sink: &mut S)
+        -> Option<usize>
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>, S: RunSink<Item>
+    {
+        if !self.seek_front(on_panic, &mut predicate) {
+            return None;
+        }
+        sink.reserve_run(1);
+        let start = self.slot(self.pos);
+        self.pos += 1;
+        let mut run = RunGuard { sink, start, len: 1 };
+        let mut keep_next = false;
+        while let Some(decision) = self.decide_front(on_panic, &mut predicate) {
+            if decision.is_drain() {
+                run.sink.reserve_run(run.len + 1);
+                self.pos += 1;
+                run.len += 1;
+            } else {
+                keep_next = true;
+                break;
+            }
+        }
+        let (start, len) = (run.start, run.len);
+        mem::forget(run);
+        unsafe { sink.take_run(start, len) }
+        if keep_next {
+            self.keep_front(1);
+        }
+        Some(len)
+    }
+
+    /// Like `next_front` but visits the elements from the back.
+    ///
+    /// Elements the predicate decides to keep are moved to the
+    /// back gap.
+    pub(crate) fn next_back<F, D>(&mut self, on_panic: OnPanic, mut predicate: F)
+        -> Option<(usize, Item)>
+        where F: FnMut(usize, &mut Item) -> D, D: Into<Decision>
+    {
+        while !self.stopped && self.pos < self.end {
+            let idx = self.end - 1;
+            let decision = self.decide(idx, true, on_panic, &mut predicate);
+            self.stopped = decision.is_stop();
+            unsafe {
+                self.end -= 1;
+                let current = self.slot(idx);
+                if decision.is_drain() {
+                    return Some((idx, ptr::read(current)));
+                } else {
+                    self.back_gap_pos -= 1;
+                    if self.end < self.back_gap_pos {
+                        let gap = self.slot(self.back_gap_pos);
+                        ptr::copy_nonoverlapping(current, gap, 1);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Drops all elements not yet visited.
+    ///
+    /// If dropping an element panics the other elements are
+    /// still dropped.
+    pub(crate) fn drop_rest(&mut self) {
+        let rem_len = self.remaining();
+        let start = self.slot(self.pos);
+        // mark them as visited first so that they are not
+        // moved back into the vector if a drop panics
+        self.pos = self.end;
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(start, rem_len));
+        }
+    }
+}
+
+impl<'a, Item: 'a> RawDrain<'a, Item> {
+
+    /// Stops draining and returns the (consistent) vector.
+    pub(crate) fn into_vec(self) -> &'a mut Vec<Item> {
+        let mut this = ManuallyDrop::new(self);
+        this.stitch();
+        unsafe { ptr::read(&this.vec) }
+    }
+
+    /// Moves the elements not yet visited and the elements
+    /// kept from the back to the gap (still) left from draining
+    /// elements and then sets the new length.
+    ///
+    /// I.e. it will undo the leak amplification.
+    fn stitch(&mut self) {
+        let rem_len = self.end - self.pos;
+        let back_len = self.len - self.back_gap_pos;
+        unsafe {
+            if self.gap_pos < self.pos {
+                let src = self.slot(self.pos);
+                let dest = self.slot(self.gap_pos);
+                ptr::copy(src, dest, rem_len);
+            }
+            let new_end = self.gap_pos + rem_len;
+            if new_end < self.back_gap_pos {
+                let src = self.slot(self.back_gap_pos);
+                let dest = self.slot(new_end);
+                ptr::copy(src, dest, back_len);
+            }
+            self.vec.set_len(new_end + back_len);
+        }
+    }
+}
+
+impl<'a, Item: 'a> Drop for RawDrain<'a, Item> {
+    fn drop(&mut self) {
+        self.stitch();
+    }
+}
+
+/// Takes over runs of elements drained in place, see `RawDrain::next_run`.
+pub(crate) trait RunSink<Item> {
+
+    /// Called before the run grows to `len` elements.
+    fn reserve_run(&mut self, _len: usize) {}
+
+    /// Takes over the ownership of the `len` elements of a completed
+    /// run starting at `start`.
+    ///
+    /// The elements are only valid until this returns.
+    unsafe fn take_run(&mut self, start: *mut Item, len: usize);
+
+    /// Like `take_run` but for the part of a run collected before the
+    /// predicate panicked, by default the elements are dropped.
+    unsafe fn abort_run(&mut self, start: *mut Item, len: usize) {
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(start, len))
+    }
+}
+
+/// Hands the part of the run collected so far to the sink if the
+/// predicate panics.
+struct RunGuard<'s, Item, S: 's + RunSink<Item>> {
+    sink: &'s mut S,
+    start: *mut Item,
+    len: usize
+}
+
+impl<'s, Item, S: 's + RunSink<Item>> Drop for RunGuard<'s, Item, S> {
+    fn drop(&mut self) {
+        unsafe { self.sink.abort_run(self.start, self.len) }
+    }
+}
+
+/// Handles the element the predicate is called on if the predicate panics.
+struct PanicGuard<'r, 'a: 'r, Item: 'a> {
+    raw: &'r mut RawDrain<'a, Item>,
+    idx: usize,
+    from_back: bool,
+    on_panic: OnPanic
+}
+
+impl<'r, 'a: 'r, Item: 'a> Drop for PanicGuard<'r, 'a, Item> {
+    fn drop(&mut self) {
+        if self.on_panic == OnPanic::Keep {
+            // it's still in the not yet visited section
+            return;
+        }
+        // move it into the gap, so that it's no longer owned by the vector
+        if self.from_back {
+            self.raw.end -= 1;
+        } else {
+            self.raw.pos += 1;
+        }
+        if self.on_panic == OnPanic::Drop {
+            unsafe { ptr::drop_in_place(self.raw.slot(self.idx)) }
+        }
+    }
+}
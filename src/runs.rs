@@ -0,0 +1,287 @@
+//! Draining runs of consecutive matching elements.
+use std::ptr;
+use std::iter::FusedIterator;
+
+use raw::{RawDrain, RunSink};
+use OnPanic;
+
+/// Iterator draining runs of consecutive elements the predicate is true for.
+///
+/// See `VecDrainWhereExt::e_drain_runs_where`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecDrainRunsWhere<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred
+}
+
+impl<'a, I: 'a, P> VecDrainRunsWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        VecDrainRunsWhere { raw, predicate }
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecDrainRunsWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        if !self.raw.seek_front(OnPanic::Leak, |_, item| predicate(item)) {
+            return None;
+        }
+        let mut run = Vec::new();
+        run.extend(self.raw.take_front());
+        while let Some(decision) = self.raw.decide_front(OnPanic::Leak, |_, item| predicate(item)) {
+            if decision.is_drain() {
+                run.extend(self.raw.take_front());
+            } else {
+                self.raw.keep_front(1);
+                break;
+            }
+        }
+        Some(run)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.raw.remaining()))
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecDrainRunsWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{}
+
+/// Passes runs to a callback and drops them afterwards (including
+/// if the callback panics).
+struct CallbackSink<F>(F);
+
+impl<I, F> RunSink<I> for CallbackSink<F>
+    where F: FnMut(&mut [I])
+{
+    unsafe fn take_run(&mut self, start: *mut I, len: usize) {
+        let run = ptr::slice_from_raw_parts_mut(start, len);
+        let _dropper = DropRun(run);
+        (self.0)(&mut *run);
+    }
+}
+
+struct DropRun<I>(*mut [I]);
+
+impl<I> Drop for DropRun<I> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0) }
+    }
+}
+
+/// See `VecDrainWhereExt::e_drain_runs_where_with`.
+pub(crate) fn drain_runs_with<I, P, F>(vec: &mut Vec<I>, mut predicate: P, on_run: F) -> usize
+    where P: FnMut(&mut I) -> bool, F: FnMut(&mut [I])
+{
+    let mut raw = RawDrain::new(vec, ..);
+    let mut sink = CallbackSink(on_run);
+    let mut runs = 0;
+    while raw.next_run(OnPanic::Leak, |_, item| predicate(item), &mut sink).is_some() {
+        runs += 1;
+    }
+    runs
+}
+
+/// Iterator splitting a vector into segments separated by elements
+/// the predicate is true for.
+///
+/// See `VecDrainWhereExt::e_split_where`.
+#[must_use]
+#[derive(Debug)]
+pub struct VecSplitWhere<'a, Item: 'a, Pred> {
+    raw: RawDrain<'a, Item>,
+    predicate: Pred,
+    done: bool
+}
+
+impl<'a, I: 'a, P> VecSplitWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    pub(crate) fn new(raw: RawDrain<'a, I>, predicate: P) -> Self {
+        VecSplitWhere { raw, predicate, done: false }
+    }
+}
+
+impl<'a, I: 'a, P> Iterator for VecSplitWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // the segment is kept until the separator is found, so if
+        // the predicate panics it stays in the vector
+        let segment_start = self.raw.front_gap_idx();
+        let predicate = &mut self.predicate;
+        while let Some(decision) = self.raw.decide_front(OnPanic::Leak, |_, item| predicate(item)) {
+            if decision.is_drain() {
+                self.raw.take_front();
+                return Some(self.raw.take_kept_since(segment_start));
+            }
+            self.raw.keep_front(1);
+        }
+        self.done = true;
+        Some(self.raw.take_kept_since(segment_start))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (1, Some(self.raw.remaining() + 1))
+        }
+    }
+}
+
+impl<'a, I: 'a, P> FusedIterator for VecSplitWhere<'a, I, P>
+    where P: FnMut(&mut I) -> bool
+{}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn expected_runs(mask: &[bool]) -> Vec<Vec<usize>> {
+        let mut runs = Vec::new();
+        let mut current = Vec::new();
+        for (idx, matches) in mask.iter().enumerate() {
+            if *matches {
+                current.push(idx);
+            } else if !current.is_empty() {
+                runs.push(::std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            runs.push(current);
+        }
+        runs
+    }
+
+    fn runs_with_mask(mask: Vec<bool>) -> TestResult {
+        let expected = expected_runs(&mask);
+        let expected_rest = (0..mask.len()).filter(|el| !mask[*el]).collect::<Vec<_>>();
+
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let runs = data.e_drain_runs_where(|el| mask[*el]).collect::<Vec<_>>();
+        if runs != expected || data != expected_rest {
+            return TestResult::error(format!("runs {:?}/{:?}, exp {:?}", runs, data, expected));
+        }
+
+        let counter = Rc::new(());
+        let mut data = (0..mask.len()).map(|el| (el, counter.clone())).collect::<Vec<_>>();
+        let mut runs = Vec::new();
+        let count = data.e_drain_runs_where_with(|el| mask[el.0], |run| {
+            runs.push(run.iter().map(|el| el.0).collect::<Vec<_>>());
+        });
+        if runs != expected || count != expected.len()
+            || data.iter().map(|el| el.0).collect::<Vec<_>>() != expected_rest
+        {
+            return TestResult::error(format!("in place runs {:?}, exp {:?}", runs, expected));
+        }
+        drop(data);
+        if Rc::strong_count(&counter) != 1 {
+            return TestResult::error("leaked elements");
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_runs_with_mask() {
+        ::quickcheck::quickcheck(runs_with_mask as fn(Vec<bool>) -> TestResult);
+    }
+
+    #[test]
+    fn in_place_runs_can_be_mutated() {
+        let mut data = vec![1, 2, 3, 5, 6, 8, 10, 12, 13];
+        let mut sums = Vec::new();
+        data.e_drain_runs_where_with(|x| *x % 2 == 0, |run| {
+            run.reverse();
+            sums.push(run.iter().sum::<i32>());
+        });
+        assert_eq!(sums, vec![2, 36]);
+        assert_eq!(data, vec![1, 3, 5, 13]);
+    }
+
+    #[test]
+    fn in_place_panic_drops_partial_run() {
+        let counter = Rc::new(());
+        let mut data = [2, 4, 5, 6].iter().map(|el| (*el, counter.clone())).collect::<Vec<_>>();
+        let mut called = false;
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            data.e_drain_runs_where_with(|el| {
+                if el.0 == 5 { panic!("5") }
+                el.0 % 2 == 0
+            }, |_| called = true)
+        }));
+        assert!(res.is_err());
+        assert!(!called);
+        // the element the predicate panicked on is leaked
+        assert_eq!(data.iter().map(|el| el.0).collect::<Vec<_>>(), vec![6]);
+        drop(data);
+        assert_eq!(Rc::strong_count(&counter), 2);
+    }
+
+    #[test]
+    fn stop_between_runs() {
+        let mut data = vec![1, 2, 2, 3, 4, 4, 5];
+        assert_eq!(data.e_drain_runs_where(|x| *x % 2 == 0).next(), Some(vec![2, 2]));
+        assert_eq!(data, vec![1, 3, 4, 4, 5]);
+    }
+
+    fn split_like_slice_split(data: Vec<u8>) -> TestResult {
+        let expected = data.split(|x| *x % 3 == 0).map(|s| s.to_vec()).collect::<Vec<_>>();
+        let mut data = data;
+        let segments = data.e_split_where(|x| *x % 3 == 0).collect::<Vec<_>>();
+        if segments != expected || !data.is_empty() {
+            return TestResult::error(format!("{:?} != {:?}", segments, expected));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_split_like_slice_split() {
+        ::quickcheck::quickcheck(split_like_slice_split as fn(Vec<u8>) -> TestResult);
+    }
+
+    #[test]
+    fn split_panic_keeps_unyielded_segment() {
+        let mut data = vec![(1, "a"), (0, "sep"), (2, "b"), (0, "sep"), (3, "c")];
+        let mut segments = Vec::new();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            let mut seen = 0;
+            for segment in data.e_split_where(|el| {
+                if el.0 == 0 {
+                    seen += 1;
+                    if seen == 2 { panic!("second separator") }
+                }
+                el.0 == 0
+            }) {
+                segments.push(segment);
+            }
+        }));
+        assert!(res.is_err());
+        assert_eq!(segments, vec![vec![(1, "a")]]);
+        // the separator the predicate panicked on is leaked
+        assert_eq!(data, vec![(2, "b"), (3, "c")]);
+    }
+
+    #[test]
+    fn split_stopped_early_keeps_rest() {
+        let mut data = vec![1, 2, 0, 3, 0, 4];
+        assert_eq!(data.e_split_where(|x| *x == 0).next(), Some(vec![1, 2]));
+        assert_eq!(data, vec![3, 0, 4]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Moving matching elements directly into another collection.
+use std::ptr;
+
+use raw::{RawDrain, RunSink};
+use OnPanic;
+
+/// Moves runs to the end of the vector.
+///
+/// Space for the whole run is reserved before an element is added to
+/// it, so taking the run (e.g. because of a panic) never allocates.
+impl<I> RunSink<I> for Vec<I> {
+    fn reserve_run(&mut self, len: usize) {
+        self.reserve(len);
+    }
+
+    unsafe fn take_run(&mut self, start: *mut I, len: usize) {
+        let old_len = self.len();
+        ptr::copy_nonoverlapping(start, self.as_mut_ptr().add(old_len), len);
+        self.set_len(old_len + len);
+    }
+
+    unsafe fn abort_run(&mut self, start: *mut I, len: usize) {
+        self.take_run(start, len)
+    }
+}
+
+/// See `VecDrainWhereExt::e_move_where_into`.
+pub(crate) fn move_where_into<I, P>(vec: &mut Vec<I>, target: &mut Vec<I>, mut predicate: P) -> usize
+    where P: FnMut(&mut I) -> bool
+{
+    let mut raw = RawDrain::new(vec, ..);
+    let mut moved = 0;
+    while let Some(len) = raw.next_run(OnPanic::Leak, |_, item| predicate(item), target) {
+        moved += len;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::collections::VecDeque;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    fn move_with_mask(mask: Vec<bool>, prefix: usize) -> TestResult {
+        let prefix = prefix % 8;
+        let expected_moved = (0..mask.len()).filter(|el| mask[*el]).collect::<Vec<_>>();
+        let expected_rest = (0..mask.len()).filter(|el| !mask[*el]).collect::<Vec<_>>();
+
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut target = (0..prefix).map(|el| el + 1000).collect::<Vec<_>>();
+        let moved = data.e_move_where_into(&mut target, |el| mask[*el]);
+        if moved != expected_moved.len() || target[prefix..] != expected_moved[..]
+            || target[..prefix].iter().cloned().ne((0..prefix).map(|el| el + 1000))
+            || data != expected_rest
+        {
+            return TestResult::error(format!("bulk: {:?}/{:?}", target, data));
+        }
+
+        let mut data = (0..mask.len()).collect::<Vec<_>>();
+        let mut target = VecDeque::new();
+        let moved = data.e_move_where(&mut target, |el| mask[*el]);
+        if moved != expected_moved.len() || target.into_iter().ne(expected_moved)
+            || data != expected_rest
+        {
+            return TestResult::error(format!("extend: {:?}", data));
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_move_with_mask() {
+        ::quickcheck::quickcheck(move_with_mask as fn(Vec<bool>, usize) -> TestResult);
+    }
+
+    #[test]
+    fn zero_sized_runs() {
+        let mut data = vec![(); 10];
+        let mut target = vec![(); 2];
+        let mut idx = 0;
+        let moved = data.e_move_where_into(&mut target, |_| { idx += 1; idx % 3 != 0 });
+        assert_eq!(moved, 7);
+        assert_eq!(target.len(), 9);
+        assert_eq!(data.len(), 3);
+    }
+
+    #[test]
+    fn panic_moves_current_run() {
+        let counter = Rc::new(());
+        let mut data = (0..8).map(|el| (el, counter.clone())).collect::<Vec<_>>();
+        let mut target = Vec::new();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            data.e_move_where_into(&mut target, |el| {
+                if el.0 == 5 { panic!("5") }
+                el.0 != 2
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(target.iter().map(|el| el.0).collect::<Vec<_>>(), vec![0, 1, 3, 4]);
+        // the element the predicate panicked on is leaked
+        assert_eq!(data.iter().map(|el| el.0).collect::<Vec<_>>(), vec![2, 6, 7]);
+        drop(data);
+        drop(target);
+        assert_eq!(Rc::strong_count(&counter), 2);
+    }
+}
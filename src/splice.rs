@@ -0,0 +1,141 @@
+//! Replacing matching elements with zero or more elements in place.
+use std::collections::VecDeque;
+
+use raw::RawDrain;
+use OnPanic;
+
+/// Elements which need to be inserted after the kept elements
+/// but for which there is no space in the gap (yet).
+///
+/// When dropped (including because of a panic) the vector is
+/// stitched together and the pending elements are inserted.
+struct SpliceState<'a, Item: 'a> {
+    raw: Option<RawDrain<'a, Item>>,
+    pending: VecDeque<Item>
+}
+
+impl<'a, I: 'a> SpliceState<'a, I> {
+
+    /// Moves pending elements into the gap as long as there is space.
+    fn flush(&mut self) {
+        let raw = self.raw.as_mut().expect("only taken on drop");
+        while raw.front_gap_len() > 0 {
+            match self.pending.pop_front() {
+                Some(item) => raw.put_front(item),
+                None => break
+            }
+        }
+    }
+}
+
+impl<'a, I: 'a> Drop for SpliceState<'a, I> {
+    fn drop(&mut self) {
+        if let Some(raw) = self.raw.take() {
+            let at = raw.front_gap_idx();
+            let vec = raw.into_vec();
+            if !self.pending.is_empty() {
+                vec.splice(at..at, self.pending.drain(..));
+            }
+        }
+    }
+}
+
+/// See `VecDrainWhereExt::e_splice_where`.
+pub(crate) fn splice_where<I, F, R, Rep>(vec: &mut Vec<I>, mut predicate: F, mut replace_with: R)
+    -> usize
+    where F: FnMut(&mut I) -> bool, R: FnMut(I) -> Rep, Rep: IntoIterator<Item=I>
+{
+    let mut state = SpliceState {
+        raw: Some(RawDrain::new(vec, ..)),
+        pending: VecDeque::new()
+    };
+    let mut replaced = 0;
+    loop {
+        let decision = {
+            let raw = state.raw.as_mut().expect("only taken on drop");
+            match raw.decide_front(OnPanic::Leak, |_, item| predicate(item)) {
+                Some(decision) => decision,
+                None => break
+            }
+        };
+        if decision.is_drain() {
+            let item = state.raw.as_mut().and_then(|raw| raw.take_front())
+                .expect("decided on element");
+            replaced += 1;
+            for new_item in replace_with(item) {
+                state.pending.push_back(new_item);
+                state.flush();
+            }
+        } else if state.pending.is_empty() {
+            state.raw.as_mut().expect("only taken on drop").keep_front(1);
+        } else {
+            // it has to be placed after the pending elements
+            let item = state.raw.as_mut().and_then(|raw| raw.take_front())
+                .expect("decided on element");
+            state.pending.push_back(item);
+            state.flush();
+        }
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use quickcheck::TestResult;
+    use VecDrainWhereExt;
+
+    #[test]
+    fn expand_and_remove() {
+        let mut data = vec![1, 2, 3, 4, 5];
+        let replaced = data.e_splice_where(|x| *x % 2 == 0, |x| vec![x * 10; x / 2]);
+        assert_eq!(replaced, 2);
+        assert_eq!(data, vec![1, 20, 3, 40, 40, 5]);
+
+        let replaced = data.e_splice_where(|x| *x == 40, |_| None);
+        assert_eq!(replaced, 2);
+        assert_eq!(data, vec![1, 20, 3, 5]);
+    }
+
+    fn splice_like_flat_map(counts: Vec<u8>, panic_at: Option<usize>) -> TestResult {
+        let counter = Rc::new(());
+        let mut data = (0..counts.len()).map(|idx| (idx, 0, counter.clone())).collect::<Vec<_>>();
+        let res = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            data.e_splice_where(|el| counts[el.0] % 4 != 1, |(idx, _, rc)| {
+                if Some(idx) == panic_at {
+                    panic!("-- yes panic --");
+                }
+                (0..counts[idx] % 4).map(|n| (idx, n + 1, rc.clone())).collect::<Vec<_>>()
+            })
+        }));
+        let panicked = panic_at.map(|idx| idx < counts.len() && counts[idx] % 4 != 1).unwrap_or(false);
+        if res.is_err() != panicked {
+            return TestResult::error("unexpected panic result");
+        }
+        let expected = (0..counts.len()).flat_map(|idx| {
+            let count = counts[idx] % 4;
+            let stopped_at = if panicked { panic_at } else { None };
+            if Some(idx) == stopped_at {
+                vec![]
+            } else if count == 1 || stopped_at.map(|p| idx > p).unwrap_or(false) {
+                vec![(idx, 0)]
+            } else {
+                (0..count).map(|n| (idx, n + 1)).collect::<Vec<_>>()
+            }
+        }).collect::<Vec<_>>();
+        let result = data.iter().map(|el| (el.0, el.1)).collect::<Vec<_>>();
+        if result != expected {
+            return TestResult::error(format!("result {:?}, exp {:?}", result, expected));
+        }
+        drop(data);
+        if Rc::strong_count(&counter) != 1 {
+            return TestResult::error("leaked elements");
+        }
+        TestResult::passed()
+    }
+
+    #[test]
+    fn qc_splice_like_flat_map() {
+        ::quickcheck::quickcheck(splice_like_flat_map as fn(Vec<u8>, Option<usize>) -> TestResult);
+    }
+}